@@ -0,0 +1,363 @@
+use crate::{
+    Capabilities, Milliwatts, PdVersion, PowerConfig, ProtocolIndication,
+    ProtocolIndicationResponse, ProtocolStatus, VoltageStatus, Watts,
+};
+
+/// Margin kept above the measured power of a port
+const HEADROOM: Watts = Watts(5);
+/// Most a port takes while its output is at 5 V, 5 V × 3 A
+const MAX_AT_5V: Watts = Watts(15);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllocationPolicy {
+    /// The budget is split evenly between the attached ports
+    EqualShare,
+    /// Ports attached first get as much as they can take
+    FirstCome,
+    /// Ports get as much as they can take in the order of their priority
+    FixedPriority,
+}
+
+/// State of one port read before an allocation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortReading {
+    pub attached: bool,
+    pub protocol: ProtocolIndicationResponse,
+    /// Measured output power
+    pub power: Milliwatts,
+    /// Output limit in effect, set by Rset or reg0xA7
+    pub limit: Watts,
+}
+
+impl PortReading {
+    /// Reading of a port without sink.
+    pub const DETACHED: Self = Self {
+        attached: false,
+        protocol: ProtocolIndicationResponse {
+            protocol_status: ProtocolStatus::OffLine,
+            voltage_status: VoltageStatus::_5V,
+            pd_version: PdVersion::Unknown,
+            protocol: ProtocolIndication::Unknown,
+        },
+        power: Milliwatts::ZERO,
+        limit: PowerConfig::MIN,
+    };
+
+    /// Power the port asks for.
+    ///
+    /// A port drawing close to its limit asks for as much as it can take: the most of `capabilities`,
+    /// or 15 W while its output is at 5 V. Otherwise it asks for its measured power plus a margin.
+    pub fn demand(&self, capabilities: Capabilities) -> Watts {
+        if !self.attached {
+            return capabilities.min_power;
+        }
+
+        let most = match self.protocol.voltage_status {
+            VoltageStatus::_5V => MAX_AT_5V.min(capabilities.max_power),
+            VoltageStatus::ProtocolVoltage => capabilities.max_power,
+        };
+        let wanted = self.power.0.div_ceil(1000) + HEADROOM.0 as u32;
+        if wanted >= self.limit.0 as u32 {
+            most
+        } else {
+            Watts(wanted as u8).clamp(capabilities.min_power, most)
+        }
+    }
+}
+
+/// Result of `SW3526::allocate_power`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Allocation<const N: usize> {
+    pub readings: [PortReading; N],
+    /// Limits applied
+    pub limits: [Watts; N],
+}
+
+/// Splits a total power budget between `N` ports.
+///
+/// Every port, attached or not, keeps at least the least of the [`Capabilities`] as the chip can not go lower,
+/// so the sum of the limits never exceeds the budget even when a port attaches between two allocations.
+/// The rest of the budget goes to the attached ports according to the policy, first up to
+/// their [`PortReading::demand`], then up to the most of the capabilities.
+/// A port without a fast charge protocol or drawing less than it is given does not hold back the others.
+#[derive(Debug, Clone)]
+pub struct PowerAllocator<const N: usize> {
+    budget: Watts,
+    policy: AllocationPolicy,
+    capabilities: Capabilities,
+    /// Lower value first, ties are broken by port index
+    priorities: [u8; N],
+    attach_order: [Option<u32>; N],
+    next_attach: u32,
+}
+
+impl<const N: usize> PowerAllocator<N> {
+    /// `capabilities` are those of the chip of every port, see `SW3526::capabilities`.
+    /// `None` if `budget` can not give the least of `capabilities` to every port.
+    pub fn new(
+        budget: Watts,
+        policy: AllocationPolicy,
+        capabilities: Capabilities,
+    ) -> Option<Self> {
+        if (budget.0 as usize) < capabilities.min_power.0 as usize * N {
+            return None;
+        }
+
+        Some(Self {
+            budget,
+            policy,
+            capabilities,
+            priorities: [0; N],
+            attach_order: [None; N],
+            next_attach: 0,
+        })
+    }
+
+    /// Priorities used by [`AllocationPolicy::FixedPriority`], lower value first.
+    pub fn with_priorities(mut self, priorities: [u8; N]) -> Self {
+        self.priorities = priorities;
+        self
+    }
+
+    pub fn budget(&self) -> Watts {
+        self.budget
+    }
+
+    pub fn policy(&self) -> AllocationPolicy {
+        self.policy
+    }
+
+    /// Compute the limit of every port.
+    pub fn allocate(&mut self, readings: &[PortReading; N]) -> [Watts; N] {
+        for (order, reading) in self.attach_order.iter_mut().zip(readings) {
+            match (*order, reading.attached) {
+                (None, true) => {
+                    *order = Some(self.next_attach);
+                    self.next_attach = self.next_attach.wrapping_add(1);
+                }
+                (Some(_), false) => *order = None,
+                _ => {}
+            }
+        }
+
+        let min_power = self.capabilities.min_power;
+        let mut limits = [min_power; N];
+        let mut remaining = self.budget.0 - min_power.0 * N as u8;
+
+        let mut ports = [0; N];
+        for (index, port) in ports.iter_mut().enumerate() {
+            *port = index;
+        }
+        match self.policy {
+            AllocationPolicy::EqualShare => {}
+            AllocationPolicy::FirstCome => ports.sort_unstable_by_key(|&i| self.attach_order[i]),
+            AllocationPolicy::FixedPriority => {
+                ports.sort_unstable_by_key(|&i| (self.priorities[i], i))
+            }
+        }
+
+        let demands = readings.map(|reading| reading.demand(self.capabilities));
+        remaining = self.fill(&mut limits, &ports, readings, &demands, remaining);
+        self.fill(
+            &mut limits,
+            &ports,
+            readings,
+            &[self.capabilities.max_power; N],
+            remaining,
+        );
+
+        limits
+    }
+
+    /// Give `remaining` watts to the attached ports in the order of `ports`, up to `caps`.
+    /// Returns what is left.
+    fn fill(
+        &self,
+        limits: &mut [Watts; N],
+        ports: &[usize; N],
+        readings: &[PortReading; N],
+        caps: &[Watts; N],
+        mut remaining: u8,
+    ) -> u8 {
+        let attached = |index: &&usize| readings[**index].attached;
+
+        match self.policy {
+            AllocationPolicy::EqualShare => {
+                // one watt at a time so the remainder is spread too
+                while remaining > 0 {
+                    let mut given = false;
+                    for &port in ports.iter().filter(attached) {
+                        if remaining > 0 && limits[port] < caps[port] {
+                            limits[port].0 += 1;
+                            remaining -= 1;
+                            given = true;
+                        }
+                    }
+                    if !given {
+                        break;
+                    }
+                }
+            }
+            AllocationPolicy::FirstCome | AllocationPolicy::FixedPriority => {
+                for &port in ports.iter().filter(attached) {
+                    let extra = caps[port].0.saturating_sub(limits[port].0).min(remaining);
+                    limits[port].0 += extra;
+                    remaining -= extra;
+                }
+            }
+        }
+
+        remaining
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Chip, Sw3526};
+
+    const CAPABILITIES: Capabilities = Sw3526::CAPABILITIES;
+
+    fn reading(attached: bool) -> PortReading {
+        PortReading {
+            attached,
+            protocol: 0x00.try_into().unwrap(),
+            power: Milliwatts::ZERO,
+            limit: Watts(65),
+        }
+    }
+
+    fn drawing(protocol: u8, power: u32, limit: u8) -> PortReading {
+        PortReading {
+            attached: true,
+            protocol: protocol.try_into().unwrap(),
+            power: Milliwatts(power),
+            limit: Watts(limit),
+        }
+    }
+
+    #[test]
+    fn demand() {
+        assert_eq!(
+            PortReading::DETACHED.demand(CAPABILITIES),
+            CAPABILITIES.min_power
+        );
+        // PD at 9 V, well under and close to the limit
+        assert_eq!(drawing(0xe5, 18_200, 45).demand(CAPABILITIES), Watts(24));
+        assert_eq!(
+            drawing(0xe5, 42_000, 45).demand(CAPABILITIES),
+            CAPABILITIES.max_power
+        );
+        // 5 V
+        assert_eq!(
+            drawing(0x00, 2_000, 45).demand(CAPABILITIES),
+            CAPABILITIES.min_power
+        );
+        assert_eq!(drawing(0x00, 11_000, 12).demand(CAPABILITIES), Watts(15));
+    }
+
+    #[test]
+    fn budget_too_small() {
+        assert!(
+            PowerAllocator::<4>::new(Watts(47), AllocationPolicy::EqualShare, CAPABILITIES)
+                .is_none()
+        );
+        assert!(
+            PowerAllocator::<4>::new(Watts(48), AllocationPolicy::EqualShare, CAPABILITIES)
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn equal_share() {
+        let mut allocator =
+            PowerAllocator::<3>::new(Watts(100), AllocationPolicy::EqualShare, CAPABILITIES)
+                .unwrap();
+
+        assert_eq!(
+            allocator.allocate(&[reading(true), reading(false), reading(true)]),
+            [Watts(44), Watts(12), Watts(44)]
+        );
+        assert_eq!(
+            allocator.allocate(&[reading(true), reading(true), reading(true)]),
+            [Watts(34), Watts(33), Watts(33)]
+        );
+        assert_eq!(
+            allocator.allocate(&[reading(false), reading(true), reading(false)]),
+            [Watts(12), Watts(71), Watts(12)]
+        );
+    }
+
+    #[test]
+    fn within_capabilities() {
+        let capabilities = Capabilities {
+            min_power: Watts(15),
+            max_power: Watts(45),
+        };
+        assert!(
+            PowerAllocator::<2>::new(Watts(29), AllocationPolicy::EqualShare, capabilities)
+                .is_none()
+        );
+        let mut allocator =
+            PowerAllocator::<2>::new(Watts(100), AllocationPolicy::EqualShare, capabilities)
+                .unwrap();
+
+        assert_eq!(
+            allocator.allocate(&[reading(true), reading(false)]),
+            [Watts(45), Watts(15)]
+        );
+    }
+
+    #[test]
+    fn first_come() {
+        let mut allocator =
+            PowerAllocator::<3>::new(Watts(120), AllocationPolicy::FirstCome, CAPABILITIES)
+                .unwrap();
+
+        allocator.allocate(&[reading(false), reading(true), reading(false)]);
+        assert_eq!(
+            allocator.allocate(&[reading(true), reading(true), reading(true)]),
+            [Watts(37), Watts(71), Watts(12)]
+        );
+
+        assert_eq!(
+            allocator.allocate(&[reading(true), reading(false), reading(true)]),
+            [Watts(71), Watts(12), Watts(37)]
+        );
+    }
+
+    #[test]
+    fn fixed_priority() {
+        let mut allocator =
+            PowerAllocator::<3>::new(Watts(100), AllocationPolicy::FixedPriority, CAPABILITIES)
+                .unwrap()
+                .with_priorities([2, 0, 1]);
+
+        assert_eq!(
+            allocator.allocate(&[reading(true), reading(true), reading(true)]),
+            [Watts(12), Watts(71), Watts(17)]
+        );
+        assert_eq!(
+            allocator.allocate(&[reading(true), reading(false), reading(true)]),
+            [Watts(17), Watts(12), Watts(71)]
+        );
+    }
+
+    #[test]
+    fn by_demand() {
+        let mut allocator =
+            PowerAllocator::<3>::new(Watts(60), AllocationPolicy::FixedPriority, CAPABILITIES)
+                .unwrap()
+                .with_priorities([0, 1, 2]);
+
+        // the first port draws little, the second uses its whole limit
+        assert_eq!(
+            allocator.allocate(&[
+                drawing(0xe5, 10_000, 45),
+                drawing(0xe5, 12_000, 12),
+                drawing(0x00, 12_000, 12),
+            ]),
+            [Watts(15), Watts(33), Watts(12)]
+        );
+    }
+}
@@ -0,0 +1,94 @@
+use crate::{ModelError, Register, VersionInfo, Watts};
+
+/// Chip version from reg0x01 bits 1~0. The datasheet does not name the revisions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ChipRevision {
+    V0,
+    V1,
+    V2,
+    V3,
+}
+
+impl From<VersionInfo> for ChipRevision {
+    fn from(value: VersionInfo) -> Self {
+        match value.version {
+            0 => Self::V0,
+            1 => Self::V1,
+            2 => Self::V2,
+            _ => Self::V3,
+        }
+    }
+}
+
+/// Features of the chip the driver depends on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Range accepted by `SW3526::set_output_limit_watts`
+    pub min_power: Watts,
+    pub max_power: Watts,
+}
+
+impl Capabilities {
+    /// Returns `ModelError::OutOfRange` if `watts` is outside of the output limit range.
+    pub fn check_power(self, watts: Watts) -> Result<(), ModelError> {
+        ModelError::check_range(
+            Register::PowerConfig,
+            watts.0 as u32,
+            self.min_power.0 as u32,
+            self.max_power.0 as u32,
+        )
+    }
+}
+
+/// Deviations of a revision from the register description, consulted by the getters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    /// Significant bits of the ADC data in reg0x3B~3C, the bits below are masked off
+    pub adc_bits: u8,
+}
+
+impl Quirks {
+    /// A revision behaving as described by the register map.
+    pub const NONE: Self = Self { adc_bits: 12 };
+
+    /// Mask the insignificant bits of the 12-bit ADC data of reg0x3B~3C.
+    pub fn adc_data(self, raw: u16) -> u16 {
+        raw & (0xfff << 12u8.saturating_sub(self.adc_bits))
+    }
+}
+
+/// Quirks of every [`ChipRevision`], indexed by the revision.
+pub type Errata = [Quirks; 4];
+
+/// Result of `SW3526::probe`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChipInfo {
+    pub name: &'static str,
+    pub revision: ChipRevision,
+    pub capabilities: Capabilities,
+    pub quirks: Quirks,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Chip, Sw3526};
+
+    #[test]
+    fn revision() {
+        assert_eq!(
+            ChipRevision::from(VersionInfo::try_from(0xfd).unwrap()),
+            ChipRevision::V1
+        );
+
+        let info = Sw3526::info(ChipRevision::V3);
+        assert_eq!(info.capabilities.max_power, Watts(71));
+        assert_eq!(info.quirks, Quirks::NONE);
+    }
+
+    #[test]
+    fn adc_data() {
+        assert_eq!(Quirks::NONE.adc_data(0xfff), 0xfff);
+        assert_eq!(Quirks { adc_bits: 10 }.adc_data(0xfff), 0xffc);
+    }
+}
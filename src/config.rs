@@ -0,0 +1,210 @@
+use crate::{
+    CCUnDrivenDurationBuckForceOff, FastChargeConfig0, FastChargeConfig1, FastChargeConfig2,
+    FastChargeConfig3, FastChargeConfig4, FastChargeConfig5, FastChargeConfig6, Register,
+    RegisterValue, Watts,
+};
+
+/// Every writable configuration of the chip, read with `SW3526::read_config`
+/// and written with `SW3526::apply_config`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sw3526Config {
+    /// reg0x13 bit 6. The force off bit clears itself, it is not part of the configuration.
+    pub cc_un_driven_duration_buck_force_off: CCUnDrivenDurationBuckForceOff,
+    pub fast_charge_0: FastChargeConfig0,
+    pub fast_charge_1: FastChargeConfig1,
+    pub fast_charge_2: FastChargeConfig2,
+    pub fast_charge_3: FastChargeConfig3,
+    pub fast_charge_4: FastChargeConfig4,
+    pub fast_charge_5: FastChargeConfig5,
+    pub fast_charge_6: FastChargeConfig6,
+    /// reg0xA7, in the range of `PowerConfig`
+    pub output_limit: Watts,
+    /// reg0xAE~AF
+    pub vid: u16,
+}
+
+impl Sw3526Config {
+    /// Fields of `actual` differing from this configuration.
+    pub fn diff(&self, actual: &Self) -> ConfigDiff {
+        ConfigDiff {
+            expected: *self,
+            actual: *actual,
+        }
+    }
+}
+
+/// A field differing between two configurations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldDiff {
+    pub register: Register,
+    /// Name of the field in the register struct, `output_limit` or `vid` for those of [`Sw3526Config`]
+    pub field: &'static str,
+}
+
+/// Result of [`Sw3526Config::diff`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConfigDiff {
+    pub expected: Sw3526Config,
+    pub actual: Sw3526Config,
+}
+
+impl ConfigDiff {
+    pub fn is_empty(&self) -> bool {
+        self.expected == self.actual
+    }
+
+    /// Differing fields in register address order.
+    pub fn fields(&self) -> impl Iterator<Item = FieldDiff> + '_ {
+        let (expected, actual) = (&self.expected, &self.actual);
+        let whole = |register, field, differs: bool| {
+            differs.then_some(FieldDiff { register, field }).into_iter()
+        };
+
+        whole(
+            Register::BuckForceOff,
+            "cc_un_driven_duration_buck_force_off",
+            expected.cc_un_driven_duration_buck_force_off
+                != actual.cc_un_driven_duration_buck_force_off,
+        )
+        .chain(changed_fields(expected.fast_charge_6, actual.fast_charge_6))
+        .chain(changed_fields(expected.fast_charge_5, actual.fast_charge_5))
+        .chain(whole(
+            Register::PowerConfig,
+            "output_limit",
+            expected.output_limit != actual.output_limit,
+        ))
+        .chain(changed_fields(expected.fast_charge_0, actual.fast_charge_0))
+        .chain(changed_fields(expected.fast_charge_1, actual.fast_charge_1))
+        .chain(changed_fields(expected.fast_charge_2, actual.fast_charge_2))
+        .chain(changed_fields(expected.fast_charge_3, actual.fast_charge_3))
+        .chain(changed_fields(expected.fast_charge_4, actual.fast_charge_4))
+        .chain(whole(
+            Register::VidConfig0,
+            "vid",
+            expected.vid != actual.vid,
+        ))
+    }
+}
+
+fn changed_fields<T: RegisterValue>(expected: T, actual: T) -> impl Iterator<Item = FieldDiff> {
+    let (expected, actual): (u8, u8) = (expected.into(), actual.into());
+    let changed = expected ^ actual;
+    T::FIELDS
+        .iter()
+        .filter(move |(_, mask)| changed & mask != 0)
+        .map(|(field, _)| FieldDiff {
+            register: T::REGISTER,
+            field,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PowerSettingMethod, Sw3526Sim, SW3526};
+
+    fn golden() -> Sw3526Config {
+        let sim = Sw3526Sim::new();
+        let mut config = SW3526::new(sim.bus()).read_config().unwrap();
+        config.fast_charge_0.vooc_disabled = true;
+        config.fast_charge_1.pd_20v_disabled = true;
+        config.fast_charge_3.power_setting_method = PowerSettingMethod::Register;
+        config.fast_charge_4.port_fast_charge_disabled = true;
+        config.output_limit = Watts(45);
+        config.vid = 0x1234;
+        config
+    }
+
+    #[test]
+    fn diff() {
+        let golden = golden();
+        let mut actual = golden;
+        assert!(golden.diff(&actual).is_empty());
+
+        actual.fast_charge_1.pd_20v_disabled = false;
+        actual.fast_charge_1.pd_9v_disabled = true;
+        actual.vid = 0x1235;
+        let diff = golden.diff(&actual);
+        assert!(!diff.is_empty());
+        assert_eq!(
+            diff.fields().collect::<Vec<_>>(),
+            [
+                FieldDiff {
+                    register: Register::FastChargeConfig1,
+                    field: "pd_20v_disabled"
+                },
+                FieldDiff {
+                    register: Register::FastChargeConfig1,
+                    field: "pd_9v_disabled"
+                },
+                FieldDiff {
+                    register: Register::VidConfig0,
+                    field: "vid"
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn apply_and_verify() {
+        let sim = Sw3526Sim::new();
+        let golden = golden();
+        // the chip is locked, apply_config sends the unlock sequence
+        let mut sw3526 = SW3526::new(sim.bus()).assume_unlocked();
+
+        assert!(sw3526.apply_config(&golden).unwrap().is_empty());
+        assert!(!sim.is_forced_off());
+        assert_eq!(sim.register(Register::VidConfig1), 0x12);
+        assert_eq!(sw3526.get_limit_watts().unwrap(), Watts(45));
+
+        sim.reset();
+        sw3526.invalidate();
+        let diff = golden.diff(&sw3526.read_config().unwrap());
+        assert_eq!(diff.fields().count(), 6);
+        assert!(diff.fields().any(|field| field.field == "output_limit"));
+    }
+
+    #[test]
+    fn read_config_out_of_range() {
+        let sim = Sw3526Sim::new();
+        let golden = golden();
+        let mut sw3526 = SW3526::new(sim.bus()).unlock().unwrap();
+        assert!(sw3526.apply_config(&golden).unwrap().is_empty());
+
+        // reg0xA7 decodes 8~11 W, below the range of the capabilities
+        sim.set_register(Register::PowerConfig, 8);
+        sw3526.invalidate();
+        let config = sw3526.read_config().unwrap();
+        assert_eq!(config.output_limit, Watts(8));
+        assert_eq!(
+            golden.diff(&config).fields().collect::<Vec<_>>(),
+            [FieldDiff {
+                register: Register::PowerConfig,
+                field: "output_limit"
+            }]
+        );
+        assert!(matches!(
+            sw3526.apply_config(&config),
+            Err(crate::OperationError::ModelError(
+                crate::ModelError::OutOfRange { value: 8, .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn invalid_config_not_written() {
+        let sim = Sw3526Sim::new();
+        let mut config = golden();
+        config.output_limit = Watts(72);
+        let mut sw3526 = SW3526::new(sim.bus()).unlock().unwrap();
+
+        assert!(matches!(
+            sw3526.apply_config(&config),
+            Err(crate::OperationError::ModelError(
+                crate::ModelError::OutOfRange { value: 72, .. }
+            ))
+        ));
+        assert_eq!(sim.register(Register::FastChargeConfig0), 0x00);
+        assert_eq!(sim.register(Register::FastChargeConfig1), 0x00);
+    }
+}
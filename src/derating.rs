@@ -0,0 +1,247 @@
+use crate::{AbnormalCaseResponse, AbnormalKind, Capabilities, ModelError, Sw3526Snapshot, Watts};
+
+/// How [`Derating`] steps the output power limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeratingPolicy {
+    /// Limit restored once the alarm is gone, in the range of the [`Capabilities`] of the chip
+    pub nominal: Watts,
+    /// Successive limits applied while the alarm stays active, from the highest to the lowest.
+    /// Each must be in the range of the [`Capabilities`] of the chip.
+    pub curve: &'static [Watts],
+    /// Time between two steps down while the alarm is active
+    pub step_down_ms: u32,
+    /// Time without alarm before the first step up
+    pub hysteresis_ms: u32,
+    /// Time between two steps up
+    pub step_up_ms: u32,
+}
+
+impl Default for DeratingPolicy {
+    fn default() -> Self {
+        Self {
+            nominal: Watts(65),
+            curve: &[Watts(45), Watts(30), Watts(18), Watts(12)],
+            step_down_ms: 5_000,
+            hysteresis_ms: 30_000,
+            step_up_ms: 10_000,
+        }
+    }
+}
+
+/// Change of the output power limit requested by [`Derating`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeratingEvent {
+    Derated { from: Watts, to: Watts },
+    Restored { from: Watts, to: Watts },
+}
+
+impl DeratingEvent {
+    /// Limit to apply.
+    pub fn limit(self) -> Watts {
+        match self {
+            Self::Derated { to, .. } | Self::Restored { to, .. } => to,
+        }
+    }
+}
+
+/// Lowers the output power limit while the over-temperature alarm is active,
+/// to keep the chip away from the over-temperature shutdown.
+///
+/// The limit steps down the curve while the alarm stays active and steps back up
+/// once it has been gone for the hysteresis time.
+/// Times come from a monotonic clock of the caller, in milliseconds.
+#[derive(Debug, Clone)]
+pub struct Derating {
+    policy: DeratingPolicy,
+    /// 0 is the nominal limit, `n` is `curve[n - 1]`
+    level: usize,
+    changed_at_ms: u64,
+    cleared_at_ms: Option<u64>,
+}
+
+impl Derating {
+    /// Returns `ModelError::OutOfRange` if a limit of the policy is outside of the range of `capabilities`,
+    /// see `SW3526::capabilities`.
+    pub fn new(policy: DeratingPolicy, capabilities: Capabilities) -> Result<Self, ModelError> {
+        capabilities.check_power(policy.nominal)?;
+        for &limit in policy.curve {
+            capabilities.check_power(limit)?;
+        }
+
+        Ok(Self {
+            policy,
+            level: 0,
+            changed_at_ms: 0,
+            cleared_at_ms: None,
+        })
+    }
+
+    /// Feed a poll of reg0x0B. Returns the change of limit to apply.
+    pub fn update(
+        &mut self,
+        abnormal_case: AbnormalCaseResponse,
+        now_ms: u64,
+    ) -> Option<DeratingEvent> {
+        let from = self.limit();
+        let elapsed_ms = now_ms.saturating_sub(self.changed_at_ms);
+
+        if abnormal_case.is_active(AbnormalKind::OverTemperatureAlarm) {
+            self.cleared_at_ms = None;
+            if self.level == self.policy.curve.len()
+                || (self.level > 0 && elapsed_ms < self.policy.step_down_ms as u64)
+            {
+                return None;
+            }
+
+            self.level += 1;
+            self.changed_at_ms = now_ms;
+            return Some(DeratingEvent::Derated {
+                from,
+                to: self.limit(),
+            });
+        }
+
+        if self.level == 0 {
+            return None;
+        }
+        let cleared_at_ms = *self.cleared_at_ms.get_or_insert(now_ms);
+        if now_ms.saturating_sub(cleared_at_ms) < self.policy.hysteresis_ms as u64
+            || elapsed_ms < self.policy.step_up_ms as u64
+        {
+            return None;
+        }
+
+        self.level -= 1;
+        self.changed_at_ms = now_ms;
+        Some(DeratingEvent::Restored {
+            from,
+            to: self.limit(),
+        })
+    }
+
+    /// Feed a snapshot, see [`Derating::update`].
+    pub fn update_snapshot(
+        &mut self,
+        snapshot: &Sw3526Snapshot,
+        now_ms: u64,
+    ) -> Option<DeratingEvent> {
+        self.update(snapshot.abnormal_case, now_ms)
+    }
+
+    /// Limit requested by the controller.
+    pub fn limit(&self) -> Watts {
+        match self.level {
+            0 => self.policy.nominal,
+            level => self.policy.curve[level - 1],
+        }
+    }
+
+    pub fn is_derated(&self) -> bool {
+        self.level > 0
+    }
+
+    pub fn policy(&self) -> &DeratingPolicy {
+        &self.policy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Chip, Sw3526};
+
+    const ALARM: u8 = 0x04;
+    const NORMAL: u8 = 0x00;
+
+    fn poll(derating: &mut Derating, abnormal: u8, now_ms: u64) -> Option<DeratingEvent> {
+        derating.update(abnormal.try_into().unwrap(), now_ms)
+    }
+
+    fn policy() -> DeratingPolicy {
+        DeratingPolicy {
+            nominal: Watts(65),
+            curve: &[Watts(45), Watts(30)],
+            step_down_ms: 1_000,
+            hysteresis_ms: 5_000,
+            step_up_ms: 2_000,
+        }
+    }
+
+    #[test]
+    fn limits_in_range() {
+        let policy = DeratingPolicy {
+            curve: &[Watts(45), Watts(10)],
+            ..policy()
+        };
+
+        assert!(matches!(
+            Derating::new(policy, Sw3526::CAPABILITIES),
+            Err(ModelError::OutOfRange { value: 10, .. })
+        ));
+    }
+
+    #[test]
+    fn limits_in_capabilities() {
+        // a chip limited to 45 W can not restore 65 W
+        let capabilities = Capabilities {
+            max_power: Watts(45),
+            ..Sw3526::CAPABILITIES
+        };
+        assert!(matches!(
+            Derating::new(policy(), capabilities),
+            Err(ModelError::OutOfRange { value: 65, .. })
+        ));
+    }
+
+    #[test]
+    fn steps_down() {
+        let mut derating = Derating::new(policy(), Sw3526::CAPABILITIES).unwrap();
+
+        assert_eq!(poll(&mut derating, NORMAL, 0), None);
+        assert_eq!(
+            poll(&mut derating, ALARM, 100),
+            Some(DeratingEvent::Derated {
+                from: Watts(65),
+                to: Watts(45)
+            })
+        );
+        assert_eq!(poll(&mut derating, ALARM, 600), None);
+        assert_eq!(
+            poll(&mut derating, ALARM, 1_100),
+            Some(DeratingEvent::Derated {
+                from: Watts(45),
+                to: Watts(30)
+            })
+        );
+        assert_eq!(poll(&mut derating, ALARM, 10_000), None);
+        assert_eq!(derating.limit(), Watts(30));
+    }
+
+    #[test]
+    fn restores_with_hysteresis() {
+        let mut derating = Derating::new(policy(), Sw3526::CAPABILITIES).unwrap();
+
+        poll(&mut derating, ALARM, 0);
+        poll(&mut derating, ALARM, 1_000);
+
+        assert_eq!(poll(&mut derating, NORMAL, 2_000), None);
+        // the alarm comes back during the hysteresis time
+        assert_eq!(poll(&mut derating, ALARM, 4_000), None);
+        assert_eq!(poll(&mut derating, NORMAL, 5_000), None);
+        assert_eq!(poll(&mut derating, NORMAL, 9_000), None);
+        assert_eq!(
+            poll(&mut derating, NORMAL, 10_000),
+            Some(DeratingEvent::Restored {
+                from: Watts(30),
+                to: Watts(45)
+            })
+        );
+        assert_eq!(poll(&mut derating, NORMAL, 11_000), None);
+        assert_eq!(
+            poll(&mut derating, NORMAL, 12_000).map(DeratingEvent::limit),
+            Some(Watts(65))
+        );
+        assert!(!derating.is_derated());
+        assert_eq!(poll(&mut derating, NORMAL, 20_000), None);
+    }
+}
@@ -1,8 +1,45 @@
 use embedded_hal::i2c;
 
+use crate::Register;
+
 #[derive(Debug)]
 pub enum ModelError {
-    InvalidValueU8(u8),
+    /// `value` read from `register` uses an encoding the model does not know.
+    InvalidRegisterValue { register: Register, value: u8 },
+    /// `value` written to `register` is outside of the range `[min, max]` accepted by the chip.
+    OutOfRange {
+        register: Register,
+        value: u32,
+        min: u32,
+        max: u32,
+    },
+    /// The value of the register pair starting at `register` kept changing while being read.
+    Unstable { register: Register },
+    /// The device answering at the address does not keep a value written to `register`,
+    /// it is not the expected chip.
+    UnknownDevice { register: Register },
+    /// `value` read from the chip specific register at `address` uses an encoding the model does not know.
+    InvalidChipRegisterValue { address: u8, value: u8 },
+}
+
+impl ModelError {
+    pub(crate) fn check_range(
+        register: Register,
+        value: u32,
+        min: u32,
+        max: u32,
+    ) -> Result<(), Self> {
+        if (min..=max).contains(&value) {
+            Ok(())
+        } else {
+            Err(Self::OutOfRange {
+                register,
+                value,
+                min,
+                max,
+            })
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -10,3 +47,15 @@ pub enum OperationError<Error: i2c::Error> {
     I2CError(Error),
     ModelError(ModelError),
 }
+
+impl<Error: i2c::Error> From<Error> for OperationError<Error> {
+    fn from(value: Error) -> Self {
+        Self::I2CError(value)
+    }
+}
+
+impl<Error: i2c::Error> From<ModelError> for OperationError<Error> {
+    fn from(value: ModelError) -> Self {
+        Self::ModelError(value)
+    }
+}
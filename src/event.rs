@@ -0,0 +1,311 @@
+use crate::ring::Ring;
+use crate::{
+    AbnormalCaseResponse, AbnormalKind, Cc, CcStatus, OutputShortCircuitStatus,
+    OverTemperatureAlarmStatus, OverTemperatureShutdownStatus, PdVersion, ProtocolIndication,
+    ProtocolIndicationResponse, ProtocolStatus, Sw3526Snapshot, SystemStatusResponse, VinOvpStatus,
+    VoltageStatus, Watts,
+};
+
+/// Change observed between two polls of the status registers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Attached {
+        cc: Cc,
+    },
+    Detached,
+    /// reg0x07 changed
+    SystemStatusChanged {
+        from: SystemStatusResponse,
+        to: SystemStatusResponse,
+    },
+    /// The indicated protocol or its PD version changed
+    ProtocolChanged {
+        from: ProtocolIndication,
+        to: ProtocolIndication,
+        pd_version: PdVersion,
+    },
+    FaultRaised(AbnormalKind),
+    FaultCleared(AbnormalKind),
+    PowerLimitChanged {
+        from: Watts,
+        to: Watts,
+    },
+}
+
+/// Registers compared by [`EventDetector`]: reg0x06, reg0x07, reg0x0B, reg0x68 and reg0x69.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventStatus {
+    pub protocol: ProtocolIndicationResponse,
+    pub system_status: SystemStatusResponse,
+    pub abnormal_case: AbnormalCaseResponse,
+    pub limit: Watts,
+    pub cc_status: CcStatus,
+}
+
+impl From<&Sw3526Snapshot> for EventStatus {
+    fn from(value: &Sw3526Snapshot) -> Self {
+        Self {
+            protocol: value.protocol,
+            system_status: value.system_status,
+            abnormal_case: value.abnormal_case,
+            limit: value.limit,
+            cc_status: value.cc_status,
+        }
+    }
+}
+
+/// Default number of events [`EventDetector`] holds until they are taken.
+pub const DEFAULT_EVENT_QUEUE_LEN: usize = 16;
+
+/// Turns successive polls of the status registers into [`Event`]s.
+///
+/// The first poll is compared against a detached port without protocol nor fault,
+/// so a sink already attached is reported as [`Event::Attached`].
+/// Events wait in a queue of `N` entries, the oldest are dropped when it is full.
+#[derive(Debug, Clone)]
+pub struct EventDetector<const N: usize = DEFAULT_EVENT_QUEUE_LEN> {
+    previous: Option<EventStatus>,
+    queue: Ring<Event, N>,
+    dropped: usize,
+}
+
+impl<const N: usize> Default for EventDetector<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> EventDetector<N> {
+    pub const fn new() -> Self {
+        Self {
+            previous: None,
+            queue: Ring::new(),
+            dropped: 0,
+        }
+    }
+
+    /// Compare `status` with the previous poll and queue the changes.
+    pub fn update(&mut self, status: EventStatus) {
+        let previous = self.previous.replace(status).unwrap_or(EventStatus {
+            protocol: ProtocolIndicationResponse {
+                protocol_status: ProtocolStatus::OffLine,
+                voltage_status: VoltageStatus::_5V,
+                pd_version: PdVersion::Unknown,
+                protocol: ProtocolIndication::Unknown,
+            },
+            abnormal_case: AbnormalCaseResponse {
+                vin_ovp_status: VinOvpStatus::Normal,
+                over_temperature_alarm_status: OverTemperatureAlarmStatus::Normal,
+                over_temperature_shutdown_status: OverTemperatureShutdownStatus::Normal,
+                output_short_circuit_status: OutputShortCircuitStatus::Normal,
+            },
+            cc_status: CcStatus {
+                cc1_attached: false,
+                cc2_attached: false,
+            },
+            ..status
+        });
+
+        match (previous.cc_status.cc(), status.cc_status.cc()) {
+            (None, Some(cc)) => self.push(Event::Attached { cc }),
+            (Some(_), None) => self.push(Event::Detached),
+            (Some(from), Some(to)) if from != to => {
+                self.push(Event::Detached);
+                self.push(Event::Attached { cc: to });
+            }
+            _ => {}
+        }
+
+        if previous.system_status != status.system_status {
+            self.push(Event::SystemStatusChanged {
+                from: previous.system_status,
+                to: status.system_status,
+            });
+        }
+
+        if previous.protocol.protocol != status.protocol.protocol
+            || previous.protocol.pd_version != status.protocol.pd_version
+        {
+            self.push(Event::ProtocolChanged {
+                from: previous.protocol.protocol,
+                to: status.protocol.protocol,
+                pd_version: status.protocol.pd_version,
+            });
+        }
+
+        for kind in AbnormalKind::ALL {
+            match (
+                previous.abnormal_case.is_active(kind),
+                status.abnormal_case.is_active(kind),
+            ) {
+                (false, true) => self.push(Event::FaultRaised(kind)),
+                (true, false) => self.push(Event::FaultCleared(kind)),
+                _ => {}
+            }
+        }
+
+        if previous.limit != status.limit {
+            self.push(Event::PowerLimitChanged {
+                from: previous.limit,
+                to: status.limit,
+            });
+        }
+    }
+
+    /// Feed a snapshot, see [`EventDetector::update`].
+    pub fn update_snapshot(&mut self, snapshot: &Sw3526Snapshot) {
+        self.update(snapshot.into())
+    }
+
+    /// Take the oldest queued event.
+    pub fn pop(&mut self) -> Option<Event> {
+        self.queue.pop()
+    }
+
+    /// Iterate over the queued events, taking them out of the queue.
+    pub fn events(&mut self) -> Events<'_, N> {
+        Events { detector: self }
+    }
+
+    /// Number of queued events.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queue.len() == 0
+    }
+
+    /// Number of events dropped because the queue was full.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    fn push(&mut self, event: Event) {
+        if !self.queue.push(event) {
+            self.dropped += 1;
+        }
+    }
+}
+
+/// Draining iterator returned by [`EventDetector::events`].
+pub struct Events<'a, const N: usize> {
+    detector: &'a mut EventDetector<N>,
+}
+
+impl<const N: usize> Iterator for Events<'_, N> {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.detector.pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(protocol: u8, abnormal: u8, limit: u8, cc: u8) -> EventStatus {
+        EventStatus {
+            protocol: protocol.try_into().unwrap(),
+            system_status: 0x03.try_into().unwrap(),
+            abnormal_case: abnormal.try_into().unwrap(),
+            limit: Watts(limit),
+            cc_status: cc.try_into().unwrap(),
+        }
+    }
+
+    #[test]
+    fn first_poll() {
+        let mut detector: EventDetector = EventDetector::new();
+
+        detector.update(status(0x00, 0x00, 65, 0x00));
+        assert!(detector.is_empty());
+
+        let mut detector: EventDetector = EventDetector::new();
+
+        detector.update(status(0xa5, 0x01, 65, 0x40));
+        assert_eq!(detector.len(), 3);
+        assert_eq!(detector.pop(), Some(Event::Attached { cc: Cc::Cc2 }));
+        assert_eq!(
+            detector.pop(),
+            Some(Event::ProtocolChanged {
+                from: ProtocolIndication::Unknown,
+                to: ProtocolIndication::PdFix,
+                pd_version: PdVersion::PD3_0
+            })
+        );
+        assert_eq!(
+            detector.pop(),
+            Some(Event::FaultRaised(AbnormalKind::OutputShortCircuit))
+        );
+        assert_eq!(detector.pop(), None);
+    }
+
+    #[test]
+    fn changes() {
+        let mut detector: EventDetector = EventDetector::new();
+
+        detector.update(status(0x00, 0x00, 65, 0x80));
+        detector.events().for_each(drop);
+
+        detector.update(status(0x00, 0x00, 65, 0x80));
+        assert!(detector.is_empty());
+
+        detector.update(status(0x82, 0x14, 45, 0x80));
+        let mut events = detector.events();
+        assert_eq!(
+            events.next(),
+            Some(Event::ProtocolChanged {
+                from: ProtocolIndication::Unknown,
+                to: ProtocolIndication::QC3_0,
+                pd_version: PdVersion::Unknown
+            })
+        );
+        assert_eq!(
+            events.next(),
+            Some(Event::FaultRaised(AbnormalKind::VinOvp))
+        );
+        assert_eq!(
+            events.next(),
+            Some(Event::FaultRaised(AbnormalKind::OverTemperatureAlarm))
+        );
+        assert_eq!(
+            events.next(),
+            Some(Event::PowerLimitChanged {
+                from: Watts(65),
+                to: Watts(45)
+            })
+        );
+        assert_eq!(events.next(), None);
+
+        detector.update(status(0x00, 0x04, 45, 0x00));
+        assert_eq!(detector.pop(), Some(Event::Detached));
+        assert!(matches!(
+            detector.pop(),
+            Some(Event::ProtocolChanged {
+                to: ProtocolIndication::Unknown,
+                ..
+            })
+        ));
+        assert_eq!(
+            detector.pop(),
+            Some(Event::FaultCleared(AbnormalKind::VinOvp))
+        );
+        assert!(detector.is_empty());
+    }
+
+    #[test]
+    fn drops_oldest() {
+        let mut detector = EventDetector::<2>::new();
+
+        detector.update(status(0xa5, 0x01, 65, 0x40));
+
+        assert_eq!(detector.len(), 2);
+        assert_eq!(detector.dropped(), 1);
+        assert!(matches!(
+            detector.pop(),
+            Some(Event::ProtocolChanged { .. })
+        ));
+    }
+}
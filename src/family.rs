@@ -0,0 +1,93 @@
+use crate::{Capabilities, ChipInfo, ChipRevision, Errata, PowerConfig, Quirks};
+
+/// A chip of the SW35xx family.
+///
+/// The driver shares the register map of the SW3526 between the chips, a chip sets its bus address,
+/// capabilities and the quirks of its revisions. Registers only a chip has, or whose fields differ
+/// on it, are described with [`ChipRegister`].
+pub trait Chip {
+    const NAME: &'static str;
+    /// 7-bit I2C address
+    const ADDRESS: u8;
+    /// Capabilities shared by the revisions of the chip
+    const CAPABILITIES: Capabilities;
+    /// Quirks of each revision, none by default
+    const ERRATA: Errata = [Quirks::NONE; 4];
+
+    /// Description of `revision`, override it for a revision differing from `CAPABILITIES`.
+    fn info(revision: ChipRevision) -> ChipInfo {
+        ChipInfo {
+            name: Self::NAME,
+            revision,
+            capabilities: Self::CAPABILITIES,
+            quirks: Self::ERRATA[revision as usize],
+        }
+    }
+}
+
+/// Register of the chip `C` differing from the map of the SW3526: a register only `C` has,
+/// or one of the map whose fields are laid out differently on `C`.
+///
+/// It is read with `Sw35xx::read_chip_value` and written with `Sw35xx::write_chip_value`,
+/// a driver of another chip does not accept it. A register of the map keeps its shadow,
+/// its getters and setters keep the layout of the SW3526.
+///
+/// ```
+/// use sw3526::{Capabilities, Chip, ChipRegister, Sw3526, Sw35xx};
+///
+/// /// A sibling of the SW3526 with a register at 0xB0
+/// struct Sibling;
+///
+/// impl Chip for Sibling {
+///     const NAME: &'static str = "sibling";
+///     const ADDRESS: u8 = 0x3c;
+///     const CAPABILITIES: Capabilities = Sw3526::CAPABILITIES;
+/// }
+///
+/// #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// struct Extra {
+///     level: u8,
+/// }
+///
+/// impl ChipRegister<Sibling> for Extra {
+///     const ADDRESS: u8 = 0xb0;
+///     const MASK: u8 = 0x0f;
+///
+///     fn from_raw(raw: u8) -> Option<Self> {
+///         Some(Self { level: raw & 0x0f })
+///     }
+///
+///     fn into_raw(self) -> u8 {
+///         self.level
+///     }
+/// }
+///
+/// fn read_extra<I2C: embedded_hal::i2c::I2c>(i2c: I2C) -> Option<Extra> {
+///     Sw35xx::<_, Sibling>::new(i2c).read_chip_value::<Extra>().ok()
+/// }
+/// ```
+pub trait ChipRegister<C: Chip>: Copy {
+    /// Address of the register on `C`
+    const ADDRESS: u8;
+    /// Bits covered by the model. The others are reserved and are preserved on write.
+    const MASK: u8;
+
+    /// Decode the register, `None` if the encoding is not used by the chip.
+    fn from_raw(raw: u8) -> Option<Self>;
+
+    /// Encode the register, the bits outside of `MASK` are ignored.
+    fn into_raw(self) -> u8;
+}
+
+/// The SW3526.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sw3526;
+
+impl Chip for Sw3526 {
+    const NAME: &'static str = "SW3526";
+    const ADDRESS: u8 = 0x3c;
+    const CAPABILITIES: Capabilities = Capabilities {
+        min_power: PowerConfig::MIN,
+        max_power: PowerConfig::MAX,
+    };
+}
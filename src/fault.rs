@@ -0,0 +1,228 @@
+use crate::ring::Ring;
+use crate::{AbnormalCaseResponse, AbnormalKind, Sw3526Snapshot};
+
+/// Default number of entries kept in the log of [`FaultMonitor`].
+pub const DEFAULT_FAULT_LOG_LEN: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultEdge {
+    Raised,
+    Cleared,
+}
+
+/// Entry of the fault log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FaultLogEntry {
+    pub at_ms: u64,
+    pub kind: AbnormalKind,
+    pub edge: FaultEdge,
+}
+
+/// History of one kind of fault.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FaultStats {
+    /// Number of times the fault was raised, saturates at `u32::MAX`
+    pub count: u32,
+    pub first_seen_ms: Option<u64>,
+    /// Last poll that reported the fault
+    pub last_seen_ms: Option<u64>,
+    /// Reported by the last poll
+    pub active: bool,
+    /// Raised and not acknowledged yet
+    pub latched: bool,
+}
+
+/// Latches the faults of reg0x0B across polls so short ones are not missed.
+///
+/// A fault stays latched until it is acknowledged and no longer active.
+/// Raise and clear edges are kept in a log of the last `N` entries.
+/// Times come from a monotonic clock of the caller, in milliseconds.
+#[derive(Debug, Clone)]
+pub struct FaultMonitor<const N: usize = DEFAULT_FAULT_LOG_LEN> {
+    stats: [FaultStats; 4],
+    log: Ring<FaultLogEntry, N>,
+}
+
+impl<const N: usize> Default for FaultMonitor<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> FaultMonitor<N> {
+    pub const fn new() -> Self {
+        const EMPTY: FaultStats = FaultStats {
+            count: 0,
+            first_seen_ms: None,
+            last_seen_ms: None,
+            active: false,
+            latched: false,
+        };
+
+        Self {
+            stats: [EMPTY; 4],
+            log: Ring::new(),
+        }
+    }
+
+    /// Feed a poll of reg0x0B.
+    pub fn update(&mut self, abnormal_case: AbnormalCaseResponse, now_ms: u64) {
+        for kind in AbnormalKind::ALL {
+            let active = abnormal_case.is_active(kind);
+            let stats = &mut self.stats[kind as usize];
+
+            let edge = match (stats.active, active) {
+                (false, true) => {
+                    stats.count = stats.count.saturating_add(1);
+                    stats.first_seen_ms.get_or_insert(now_ms);
+                    stats.latched = true;
+                    Some(FaultEdge::Raised)
+                }
+                (true, false) => Some(FaultEdge::Cleared),
+                _ => None,
+            };
+            if active {
+                stats.last_seen_ms = Some(now_ms);
+            }
+            stats.active = active;
+
+            if let Some(edge) = edge {
+                self.log.push(FaultLogEntry {
+                    at_ms: now_ms,
+                    kind,
+                    edge,
+                });
+            }
+        }
+    }
+
+    /// Feed a snapshot, see [`FaultMonitor::update`].
+    pub fn update_snapshot(&mut self, snapshot: &Sw3526Snapshot, now_ms: u64) {
+        self.update(snapshot.abnormal_case, now_ms)
+    }
+
+    pub fn stats(&self, kind: AbnormalKind) -> FaultStats {
+        self.stats[kind as usize]
+    }
+
+    pub fn is_latched(&self, kind: AbnormalKind) -> bool {
+        self.stats[kind as usize].latched
+    }
+
+    /// Faults raised and not acknowledged yet.
+    pub fn latched(&self) -> impl Iterator<Item = AbnormalKind> + '_ {
+        AbnormalKind::ALL
+            .into_iter()
+            .filter(|kind| self.is_latched(*kind))
+    }
+
+    /// Release the latch of `kind`. A fault still active stays latched, returns `false` then.
+    pub fn acknowledge(&mut self, kind: AbnormalKind) -> bool {
+        let stats = &mut self.stats[kind as usize];
+        stats.latched = stats.active;
+        !stats.latched
+    }
+
+    /// Acknowledge all faults, returns `false` if one is still active.
+    pub fn acknowledge_all(&mut self) -> bool {
+        let mut released = true;
+        for kind in AbnormalKind::ALL {
+            released &= self.acknowledge(kind);
+        }
+        released
+    }
+
+    /// Raise and clear edges, oldest first.
+    pub fn log(&self) -> impl Iterator<Item = FaultLogEntry> + '_ {
+        self.log.iter()
+    }
+
+    pub fn clear_log(&mut self) {
+        self.log.clear();
+    }
+
+    /// Forget the counters, timestamps and log. Faults active at the next poll are raised again.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poll<const N: usize>(monitor: &mut FaultMonitor<N>, abnormal: u8, now_ms: u64) {
+        monitor.update(abnormal.try_into().unwrap(), now_ms);
+    }
+
+    #[test]
+    fn latches_until_acknowledged() {
+        let mut monitor: FaultMonitor = FaultMonitor::new();
+
+        poll(&mut monitor, 0x00, 0);
+        poll(&mut monitor, 0x01, 100);
+        poll(&mut monitor, 0x00, 200);
+
+        assert!(monitor.is_latched(AbnormalKind::OutputShortCircuit));
+        assert!(monitor.latched().eq([AbnormalKind::OutputShortCircuit]));
+        assert!(monitor.acknowledge(AbnormalKind::OutputShortCircuit));
+        assert_eq!(monitor.latched().count(), 0);
+
+        poll(&mut monitor, 0x10, 300);
+        assert!(!monitor.acknowledge_all());
+        assert!(monitor.is_latched(AbnormalKind::VinOvp));
+        poll(&mut monitor, 0x00, 400);
+        assert!(monitor.acknowledge_all());
+    }
+
+    #[test]
+    fn counters() {
+        let mut monitor: FaultMonitor = FaultMonitor::new();
+
+        poll(&mut monitor, 0x04, 100);
+        poll(&mut monitor, 0x04, 200);
+        poll(&mut monitor, 0x00, 300);
+        poll(&mut monitor, 0x04, 400);
+
+        assert_eq!(
+            monitor.stats(AbnormalKind::OverTemperatureAlarm),
+            FaultStats {
+                count: 2,
+                first_seen_ms: Some(100),
+                last_seen_ms: Some(400),
+                active: true,
+                latched: true,
+            }
+        );
+        assert_eq!(
+            monitor.stats(AbnormalKind::OverTemperatureShutdown),
+            FaultStats::default()
+        );
+    }
+
+    #[test]
+    fn log() {
+        let mut monitor = FaultMonitor::<2>::new();
+
+        poll(&mut monitor, 0x02, 100);
+        poll(&mut monitor, 0x00, 200);
+        poll(&mut monitor, 0x01, 300);
+
+        assert!(monitor.log().eq([
+            FaultLogEntry {
+                at_ms: 200,
+                kind: AbnormalKind::OverTemperatureShutdown,
+                edge: FaultEdge::Cleared,
+            },
+            FaultLogEntry {
+                at_ms: 300,
+                kind: AbnormalKind::OutputShortCircuit,
+                edge: FaultEdge::Raised,
+            },
+        ]));
+
+        monitor.clear_log();
+        assert_eq!(monitor.log().count(), 0);
+        assert_eq!(monitor.stats(AbnormalKind::OutputShortCircuit).count, 1);
+    }
+}
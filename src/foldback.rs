@@ -0,0 +1,245 @@
+use crate::{Capabilities, Millivolts, ModelError, Sw3526Snapshot, Watts};
+
+/// Reduction applied while VIN is below `below`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FoldbackStage {
+    pub below: Millivolts,
+    /// Output power limit, in the range of the [`Capabilities`] of the chip
+    pub limit: Watts,
+    /// Lock the port to 5 V
+    pub disable_fast_charge: bool,
+}
+
+/// How [`Foldback`] follows the input voltage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FoldbackPolicy {
+    /// Limit restored once VIN is back up, in the range of the [`Capabilities`] of the chip
+    pub nominal: Watts,
+    /// Stages from the highest threshold to the lowest
+    pub stages: &'static [FoldbackStage],
+    /// VIN has to rise this much above a threshold to leave its stage
+    pub hysteresis: Millivolts,
+    /// Force the buck off below this voltage
+    pub cutoff: Option<Millivolts>,
+}
+
+/// Register writes requested by [`Foldback`], only the settings that changed are set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FoldbackActions {
+    /// Output power limit (reg0xA7)
+    pub limit: Option<Watts>,
+    /// `Some(true)` to lock the port to 5 V, `Some(false)` to allow fast charge again (reg0xAC)
+    pub fast_charge_disabled: Option<bool>,
+    /// `Some(true)` to force the buck off, `Some(false)` to release it (reg0x13)
+    pub buck_force_off: Option<bool>,
+}
+
+impl FoldbackActions {
+    pub const NONE: Self = Self {
+        limit: None,
+        fast_charge_disabled: None,
+        buck_force_off: None,
+    };
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::NONE
+    }
+}
+
+/// Reduces the output power when the input voltage sags, for battery or weak adapter sources.
+///
+/// The chip only forces the buck off for one second, so below the cutoff the write is repeated
+/// on every poll; poll more often than once a second.
+/// Writing reg0x13, reg0xA7, reg0xAB and reg0xAC needs `SW3526::set_i2c_writable` first.
+#[derive(Debug, Clone)]
+pub struct Foldback {
+    policy: FoldbackPolicy,
+    /// 0 is the nominal limit, `n` is `stages[n - 1]`
+    level: usize,
+    cut_off: bool,
+}
+
+impl Foldback {
+    /// Returns `ModelError::OutOfRange` if a limit of the policy is outside of the range of `capabilities`,
+    /// see `SW3526::capabilities`.
+    pub fn new(policy: FoldbackPolicy, capabilities: Capabilities) -> Result<Self, ModelError> {
+        capabilities.check_power(policy.nominal)?;
+        for stage in policy.stages {
+            capabilities.check_power(stage.limit)?;
+        }
+
+        Ok(Self {
+            policy,
+            level: 0,
+            cut_off: false,
+        })
+    }
+
+    /// Feed a measurement of VIN. Returns the writes to apply.
+    pub fn update(&mut self, vin: Millivolts) -> FoldbackActions {
+        let mut actions = FoldbackActions::NONE;
+        let (limit, fast_charge_disabled) = self.settings();
+
+        let mut level = self
+            .policy
+            .stages
+            .iter()
+            .filter(|stage| vin < stage.below)
+            .count()
+            .max(self.level);
+        while level > 0
+            && vin
+                >= self.policy.stages[level - 1]
+                    .below
+                    .saturating_add(self.policy.hysteresis)
+        {
+            level -= 1;
+        }
+        self.level = level;
+
+        let (new_limit, new_fast_charge_disabled) = self.settings();
+        if new_limit != limit {
+            actions.limit = Some(new_limit);
+        }
+        if new_fast_charge_disabled != fast_charge_disabled {
+            actions.fast_charge_disabled = Some(new_fast_charge_disabled);
+        }
+
+        if let Some(cutoff) = self.policy.cutoff {
+            if vin < cutoff {
+                self.cut_off = true;
+                actions.buck_force_off = Some(true);
+            } else if self.cut_off {
+                if vin >= cutoff.saturating_add(self.policy.hysteresis) {
+                    self.cut_off = false;
+                    actions.buck_force_off = Some(false);
+                } else {
+                    actions.buck_force_off = Some(true);
+                }
+            }
+        }
+
+        actions
+    }
+
+    /// Feed a snapshot, see [`Foldback::update`].
+    pub fn update_snapshot(&mut self, snapshot: &Sw3526Snapshot) -> FoldbackActions {
+        self.update(snapshot.input_voltage)
+    }
+
+    fn settings(&self) -> (Watts, bool) {
+        match self.level {
+            0 => (self.policy.nominal, false),
+            level => {
+                let stage = self.policy.stages[level - 1];
+                (stage.limit, stage.disable_fast_charge)
+            }
+        }
+    }
+
+    /// Limit requested by the controller.
+    pub fn limit(&self) -> Watts {
+        self.settings().0
+    }
+
+    /// Active stage, `None` at the nominal limit.
+    pub fn stage(&self) -> Option<&FoldbackStage> {
+        self.level.checked_sub(1).map(|i| &self.policy.stages[i])
+    }
+
+    pub fn is_cut_off(&self) -> bool {
+        self.cut_off
+    }
+
+    pub fn policy(&self) -> &FoldbackPolicy {
+        &self.policy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Chip, Sw3526};
+
+    const POLICY: FoldbackPolicy = FoldbackPolicy {
+        nominal: Watts(65),
+        stages: &[
+            FoldbackStage {
+                below: Millivolts(11000),
+                limit: Watts(30),
+                disable_fast_charge: false,
+            },
+            FoldbackStage {
+                below: Millivolts(10000),
+                limit: Watts(12),
+                disable_fast_charge: true,
+            },
+        ],
+        hysteresis: Millivolts(500),
+        cutoff: Some(Millivolts(9000)),
+    };
+
+    fn update(foldback: &mut Foldback, millivolts: u32) -> FoldbackActions {
+        foldback.update(Millivolts(millivolts))
+    }
+
+    #[test]
+    fn limits_in_range() {
+        let policy = FoldbackPolicy {
+            nominal: Watts(100),
+            ..POLICY
+        };
+
+        assert!(matches!(
+            Foldback::new(policy, Sw3526::CAPABILITIES),
+            Err(ModelError::OutOfRange { value: 100, .. })
+        ));
+    }
+
+    #[test]
+    fn stages_with_hysteresis() {
+        let mut foldback = Foldback::new(POLICY, Sw3526::CAPABILITIES).unwrap();
+
+        assert!(update(&mut foldback, 12000).is_empty());
+        assert_eq!(
+            update(&mut foldback, 10900),
+            FoldbackActions {
+                limit: Some(Watts(30)),
+                ..FoldbackActions::NONE
+            }
+        );
+        assert!(update(&mut foldback, 11400).is_empty());
+        assert_eq!(
+            update(&mut foldback, 9900),
+            FoldbackActions {
+                limit: Some(Watts(12)),
+                fast_charge_disabled: Some(true),
+                ..FoldbackActions::NONE
+            }
+        );
+        assert!(update(&mut foldback, 10400).is_empty());
+        // leaves both stages at once
+        assert_eq!(
+            update(&mut foldback, 11500),
+            FoldbackActions {
+                limit: Some(Watts(65)),
+                fast_charge_disabled: Some(false),
+                ..FoldbackActions::NONE
+            }
+        );
+        assert!(foldback.stage().is_none());
+    }
+
+    #[test]
+    fn cutoff() {
+        let mut foldback = Foldback::new(POLICY, Sw3526::CAPABILITIES).unwrap();
+
+        update(&mut foldback, 9500);
+        assert_eq!(update(&mut foldback, 8900).buck_force_off, Some(true));
+        assert!(foldback.is_cut_off());
+        assert_eq!(update(&mut foldback, 9400).buck_force_off, Some(true));
+        assert_eq!(update(&mut foldback, 9500).buck_force_off, Some(false));
+        assert!(!foldback.is_cut_off());
+        assert_eq!(foldback.limit(), Watts(12));
+    }
+}
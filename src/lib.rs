@@ -1,488 +1,1274 @@
 #![cfg_attr(not(test), no_std)]
 
-use embedded_hal::i2c;
+use core::marker::PhantomData;
+
+use embedded_hal::i2c::{self, Operation};
 #[cfg(not(feature = "async"))]
-use embedded_hal::i2c::I2c;
+use embedded_hal::{delay::DelayNs, i2c::I2c};
 #[cfg(feature = "async")]
-use embedded_hal_async::i2c::I2c;
+use embedded_hal_async::{delay::DelayNs, i2c::I2c};
 
+#[macro_use]
+mod register;
+pub use register::*;
 mod model;
 pub use model::*;
 mod error;
 pub use error::*;
-
-static ADDRESS: u8 = 0x3c; // fixed address
-
-pub struct SW3526<I2C> {
+mod lock;
+pub use lock::*;
+mod chip;
+pub use chip::*;
+mod config;
+pub use config::*;
+mod family;
+pub use family::*;
+mod ring;
+mod shadow;
+use shadow::Shadow;
+mod units;
+pub use units::*;
+mod snapshot;
+pub use snapshot::*;
+mod meter;
+pub use meter::*;
+mod port;
+pub use port::*;
+mod event;
+pub use event::*;
+mod fault;
+pub use fault::*;
+mod recovery;
+pub use recovery::*;
+mod derating;
+pub use derating::*;
+mod allocator;
+pub use allocator::*;
+mod foldback;
+pub use foldback::*;
+#[cfg(any(test, feature = "sim"))]
+mod sim;
+#[cfg(any(test, feature = "sim"))]
+pub use sim::*;
+#[cfg(any(test, feature = "sim"))]
+mod scenario;
+#[cfg(any(test, feature = "sim"))]
+pub use scenario::*;
+#[cfg(any(test, feature = "record"))]
+mod record;
+#[cfg(any(test, feature = "record"))]
+pub use record::*;
+
+const LIVE_PAIR_READ_ATTEMPTS: usize = 3;
+
+/// Time for the ADC to convert the newly selected channel after writing reg0x3A.
+pub const ADC_SETTLING_TIME_US: u32 = 1000;
+
+/// Driver of the SW35xx family, `C` selects the chip.
+///
+/// Registers reg0xA0~BF, reg0x70~71 and reg0x13 ignore writes until the unlock sequence is sent.
+/// `S` tracks this: the setters of these registers are only available on `Sw35xx<I2C, C, Unlocked>`,
+/// obtained with [`Sw35xx::unlock`].
+pub struct Sw35xx<I2C, C = Sw3526, S = Locked> {
     i2c: I2C,
-    adc_config: Option<AdcConfig>,
+    shadow: Shadow,
+    round_robin: AdcConfig,
+    chip: Option<ChipInfo>,
+    state: PhantomData<(C, S)>,
 }
 
+/// Driver of the SW3526.
+pub type SW3526<I2C, S = Locked> = Sw35xx<I2C, Sw3526, S>;
+
+/// Result of `Sw35xx::into_unlocked_if_writable`, the handle is given back if the chip is locked.
+pub type WritableHandle<I2C, C> = Result<Sw35xx<I2C, C, Unlocked>, Sw35xx<I2C, C, Locked>>;
+
 #[maybe_async_cfg::maybe(
-    sync(cfg(not(feature = "async")), self = "SW3526",),
+    sync(cfg(not(feature = "async")), self = "Sw35xx",),
     async(feature = "async", keep_self)
 )]
-impl<I2C, E> SW3526<I2C>
+impl<I2C, E, C> Sw35xx<I2C, C, Locked>
 where
+    C: Chip,
     E: i2c::Error,
     I2C: I2c<Error = E>,
 {
     pub fn new(i2c: I2C) -> Self {
         Self {
             i2c,
-            adc_config: None,
+            shadow: Shadow::new(),
+            round_robin: AdcConfig::Vin,
+            chip: None,
+            state: PhantomData,
         }
     }
 
+    /// Send the unlock sequence and return a handle with the setters.
     #[inline(always)]
-    pub async fn get_chip_version(&mut self) -> Result<u8, E> {
-        let mut buf = [0u8; 1];
-        self.i2c
-            .write_read(ADDRESS, &[Register::VersionInfo as u8], &mut buf)
-            .await?;
-        Ok(buf[0] & 0x03)
+    pub async fn unlock(mut self) -> Result<Sw35xx<I2C, C, Unlocked>, E> {
+        self.send_unlock_sequence().await?;
+        Ok(self.into_state())
     }
 
+    /// Handle with the setters, for a chip unlocked by someone else.
+    /// Writes are ignored if the chip is in fact locked, see [`Sw35xx::is_writable`].
+    /// Until then the protected registers are read from the chip again after being written.
+    pub fn assume_unlocked(self) -> Sw35xx<I2C, C, Unlocked> {
+        self.into_state()
+    }
+
+    /// Return a handle with the setters if the chip is already unlocked, see [`Sw35xx::is_writable`].
     #[inline(always)]
-    pub async fn get_buck_output_millivolts(&mut self) -> Result<u16, E> {
-        let mut buf_h = [0u8; 1];
-        let mut buf_l = [0u8; 1];
+    pub async fn into_unlocked_if_writable(
+        mut self,
+    ) -> Result<WritableHandle<I2C, C>, OperationError<E>> {
+        if self.is_writable().await? {
+            Ok(Ok(self.into_state()))
+        } else {
+            Ok(Err(self))
+        }
+    }
+}
 
-        self.i2c
-            .write_read(
-                ADDRESS,
-                &[Register::BuckOutputVoltageHigh8b as u8],
-                &mut buf_h,
-            )
-            .await?;
-        self.i2c
-            .write_read(
-                ADDRESS,
-                &[Register::BuckOutputVoltageLow4b as u8],
-                &mut buf_l,
-            )
-            .await?;
+#[maybe_async_cfg::maybe(
+    sync(cfg(not(feature = "async")), self = "Sw35xx",),
+    async(feature = "async", keep_self)
+)]
+impl<I2C, E, C, S> Sw35xx<I2C, C, S>
+where
+    C: Chip,
+    E: i2c::Error,
+    I2C: I2c<Error = E>,
+    S: LockState,
+{
+    /// Read a register, the shadowed ones are served from the shadow when valid.
+    #[inline(always)]
+    async fn read_register(&mut self, register: Register) -> Result<u8, E> {
+        if let Some(value) = self.shadow.get(register) {
+            return Ok(value);
+        }
 
-        Ok((((buf_h[0] as u16) << 4) | ((buf_l[0] as u16) >> 4)) * 10)
+        let value = self.read_chip_register(register).await?;
+        self.shadow.store(register, value);
+        Ok(value)
     }
 
     #[inline(always)]
-    pub async fn get_buck_output_limit_milliamps(&mut self) -> Result<u16, E> {
+    async fn read_chip_register(&mut self, register: Register) -> Result<u8, E> {
         let mut buf = [0u8; 1];
-
         self.i2c
-            .write_read(ADDRESS, &[Register::BuckOutputCurrentLimit as u8], &mut buf)
+            .write_read(C::ADDRESS, &[register as u8], &mut buf)
             .await?;
+        Ok(buf[0])
+    }
 
-        Ok(1000u16 + ((buf[0] as u16) & 0x3f) * 50)
+    #[inline(always)]
+    async fn write_register(&mut self, register: Register, value: u8) -> Result<(), E> {
+        self.i2c.write(C::ADDRESS, &[register as u8, value]).await?;
+        self.shadow.store_write(register, value);
+        Ok(())
     }
 
+    /// Read the register at `address`, through the shadow if it is a register of the map.
     #[inline(always)]
-    pub async fn get_protocol(&mut self) -> Result<ProtocolIndicationResponse, E> {
-        let mut buf = [0u8; 1];
+    async fn read_address(&mut self, address: u8) -> Result<u8, E> {
+        if let Some(register) = Register::from_address(address) {
+            return self.read_register(register).await;
+        }
 
+        let mut buf = [0u8; 1];
         self.i2c
-            .write_read(ADDRESS, &[Register::ProtocolIndication as u8], &mut buf)
+            .write_read(C::ADDRESS, &[address], &mut buf)
             .await?;
+        Ok(buf[0])
+    }
+
+    /// Write the register at `address`, through the shadow if it is a register of the map.
+    #[inline(always)]
+    async fn write_address(&mut self, address: u8, value: u8) -> Result<(), E> {
+        if let Some(register) = Register::from_address(address) {
+            return self.write_register(register, value).await;
+        }
 
-        Ok(buf[0].into())
+        self.i2c.write(C::ADDRESS, &[address, value]).await
     }
 
+    /// Read-modify-write a register.
+    /// Only the bits set in `mask` are taken from `value`, reserved and unmodelled bits keep the value read from the chip.
     #[inline(always)]
-    pub async fn get_system_status(&mut self) -> Result<SystemStatusResponse, E> {
-        let mut buf = [0u8; 1];
+    async fn update_register(&mut self, register: Register, mask: u8, value: u8) -> Result<(), E> {
+        if mask == u8::MAX {
+            return self.write_register(register, value).await;
+        }
 
-        self.i2c
-            .write_read(ADDRESS, &[Register::SystemStatus as u8], &mut buf)
-            .await?;
+        let current = self.read_register(register).await?;
+        self.write_register(register, (current & !mask) | (value & mask))
+            .await
+    }
+
+    /// Read a pair of registers holding a value the chip keeps updating.
+    /// Both registers are read in one burst and the first one is read again in the same transaction,
+    /// the whole read is retried if it changed in between.
+    #[inline(always)]
+    async fn read_live_pair(&mut self, first: Register) -> Result<[u8; 2], OperationError<E>> {
+        for _ in 0..LIVE_PAIR_READ_ATTEMPTS {
+            let mut pair = [0u8; 2];
+            let mut again = [0u8; 1];
+            self.i2c
+                .transaction(
+                    C::ADDRESS,
+                    &mut [
+                        Operation::Write(&[first as u8]),
+                        Operation::Read(&mut pair),
+                        Operation::Write(&[first as u8]),
+                        Operation::Read(&mut again),
+                    ],
+                )
+                .await?;
+
+            if pair[0] == again[0] {
+                return Ok(pair);
+            }
+        }
 
-        Ok(buf[0].into())
+        Err(ModelError::Unstable { register: first }.into())
     }
 
     #[inline(always)]
-    pub async fn get_abnormal_case(&mut self) -> Result<AbnormalCaseResponse, E> {
-        let mut buf = [0u8; 1];
+    async fn read_value<T: RegisterValue>(&mut self) -> Result<T, OperationError<E>> {
+        let raw = self.read_register(T::REGISTER).await?;
+        Ok(T::try_from(raw)?)
+    }
 
-        self.i2c
-            .write_read(ADDRESS, &[Register::AbnormalCase as u8], &mut buf)
+    /// Write the bits covered by `T`, the others keep the value read from the chip.
+    #[inline(always)]
+    async fn write_value<T: RegisterValue>(&mut self, value: T) -> Result<(), E> {
+        self.update_register(T::REGISTER, T::MASK, value.into())
+            .await
+    }
+
+    #[inline(always)]
+    async fn modify_value<T, F>(&mut self, f: F) -> Result<(), OperationError<E>>
+    where
+        T: RegisterValue,
+        F: FnOnce(&mut T),
+    {
+        let raw = self.read_register(T::REGISTER).await?;
+        let mut value = T::try_from(raw)?;
+        f(&mut value);
+        let value: u8 = value.into();
+        self.write_register(T::REGISTER, (raw & !T::MASK) | (value & T::MASK))
             .await?;
+        Ok(())
+    }
 
-        Ok(buf[0].into())
+    fn into_state<T: LockState>(self) -> Sw35xx<I2C, C, T> {
+        Sw35xx {
+            i2c: self.i2c,
+            shadow: self.shadow,
+            round_robin: self.round_robin,
+            chip: self.chip,
+            state: PhantomData,
+        }
     }
 
-    /// set i2c writable
-    /// After doing following step then reg0xA0~BF, reg0x70~71 and reg0x13 can be written by MCU
     #[inline(always)]
-    pub async fn set_i2c_writable(&mut self) -> Result<(), E> {
+    async fn send_unlock_sequence(&mut self) -> Result<(), E> {
         self.i2c
-            .write(ADDRESS, &[Register::I2cEnable as u8, 0x20])
+            .write(C::ADDRESS, &[Register::I2cEnable as u8, 0x20])
             .await?;
         self.i2c
-            .write(ADDRESS, &[Register::I2cEnable as u8, 0x40])
+            .write(C::ADDRESS, &[Register::I2cEnable as u8, 0x40])
             .await?;
         self.i2c
-            .write(ADDRESS, &[Register::I2cEnable as u8, 0x80])
+            .write(C::ADDRESS, &[Register::I2cEnable as u8, 0x80])
             .await?;
+        self.shadow.set_unlocked(true);
 
         Ok(())
     }
 
+    /// Check whether the protected registers accept writes.
+    ///
+    /// The PD command selected in reg0x70 is changed, read back and restored, even on a bus error.
+    /// The send bit stays cleared, the selection only matters once a command is sent.
     #[inline(always)]
-    pub async fn get_buck_force_off(&mut self) -> Result<BuckForceOffConfig, E> {
-        let mut buf = [0u8; 1];
-        self.i2c
-            .write_read(ADDRESS, &[Register::BuckForceOff as u8], &mut buf)
+    pub async fn is_writable(&mut self) -> Result<bool, OperationError<E>> {
+        const SEND_BIT: u8 = 0x80;
+
+        let original = self
+            .read_chip_register(Register::PowerCommandRequest)
+            .await?
+            & !SEND_BIT;
+        let probe = original ^ 0x01;
+
+        let read_back = self
+            .probe_register(Register::PowerCommandRequest, original, probe)
             .await?;
+        let writable = read_back & !SEND_BIT == probe;
+        self.shadow.set_unlocked(writable);
+        Ok(writable)
+    }
+
+    /// Write `probe` to `register`, read it back and write `original` again, even on a bus error.
+    #[inline(always)]
+    async fn probe_register(
+        &mut self,
+        register: Register,
+        original: u8,
+        probe: u8,
+    ) -> Result<u8, E> {
+        let read_back = match self.i2c.write(C::ADDRESS, &[register as u8, probe]).await {
+            Ok(()) => self.read_chip_register(register).await,
+            Err(e) => Err(e),
+        };
+        let restored = self
+            .i2c
+            .write(C::ADDRESS, &[register as u8, original])
+            .await;
+        let read_back = read_back?;
+        restored?;
 
-        Ok(buf[0].into())
+        self.shadow.store(register, original);
+        Ok(read_back)
     }
 
     #[inline(always)]
-    pub async fn set_buck_force_off(&mut self, config: BuckForceOffConfig) -> Result<(), E> {
-        self.i2c
-            .write(ADDRESS, &[Register::BuckForceOff as u8, config.into()])
-            .await
+    pub async fn get_chip_version(&mut self) -> Result<u8, OperationError<E>> {
+        let info: VersionInfo = self.read_value().await?;
+        Ok(info.version)
     }
 
     #[inline(always)]
-    pub async fn get_adc_input_millivolts(&mut self) -> Result<u16, E> {
-        let mut buf = [0u8; 1];
+    pub async fn get_chip_revision(&mut self) -> Result<ChipRevision, OperationError<E>> {
+        let info: VersionInfo = self.read_value().await?;
+        Ok(info.into())
+    }
 
-        self.i2c
-            .write_read(ADDRESS, &[Register::AdcVinData as u8], &mut buf)
+    /// Check that the chip answers at its address and identify it.
+    /// The result is kept, see [`Sw35xx::chip_info`].
+    ///
+    /// The chip has no identification register. The ADC data selection (reg0x3A), writable
+    /// while locked, is changed, read back and restored; `ModelError::UnknownDevice` is returned
+    /// if it does not keep the value.
+    #[inline(always)]
+    pub async fn probe(&mut self) -> Result<ChipInfo, OperationError<E>> {
+        let revision = self.get_chip_revision().await?;
+
+        let original = self.read_chip_register(Register::AdcConfig).await?;
+        let select = match AdcDataSelect::try_from(original).map(|select| select.config) {
+            Ok(Some(AdcConfig::Vin)) => AdcConfig::Vout,
+            _ => AdcConfig::Vin,
+        };
+        let probe = (original & !AdcDataSelect::MASK) | select as u8;
+        let read_back = self
+            .probe_register(Register::AdcConfig, original, probe)
             .await?;
-        Ok(((buf[0] as u16) << 4) * 10)
+        if read_back & AdcDataSelect::MASK != select as u8 {
+            return Err(ModelError::UnknownDevice {
+                register: Register::AdcConfig,
+            }
+            .into());
+        }
+
+        let info = C::info(revision);
+        self.chip = Some(info);
+        Ok(info)
     }
 
+    /// Read a register of `C` differing from the SW3526 map, see [`ChipRegister`].
     #[inline(always)]
-    pub async fn get_adc_output_millivolts(&mut self) -> Result<u16, E> {
-        let mut buf = [0u8; 1];
+    pub async fn read_chip_value<T: ChipRegister<C>>(&mut self) -> Result<T, OperationError<E>> {
+        let raw = self.read_address(T::ADDRESS).await?;
+        T::from_raw(raw).ok_or_else(|| {
+            ModelError::InvalidChipRegisterValue {
+                address: T::ADDRESS,
+                value: raw,
+            }
+            .into()
+        })
+    }
 
-        self.i2c
-            .write_read(ADDRESS, &[Register::AdcVoutData as u8], &mut buf)
+    /// Chip identified by the last [`Sw35xx::probe`].
+    pub fn chip_info(&self) -> Option<&ChipInfo> {
+        self.chip.as_ref()
+    }
+
+    /// Capabilities of the probed revision, those shared by the revisions of `C` before a probe.
+    pub fn capabilities(&self) -> Capabilities {
+        self.chip.map_or(C::CAPABILITIES, |info| info.capabilities)
+    }
+
+    /// Quirks of the probed revision, none before a probe.
+    pub fn quirks(&self) -> Quirks {
+        self.chip.map_or(Quirks::NONE, |info| info.quirks)
+    }
+
+    /// Encode an output limit in the range of [`Sw35xx::capabilities`].
+    fn output_limit_config(&self, watts: Watts) -> Result<PowerConfig, ModelError> {
+        self.capabilities().check_power(watts)?;
+        PowerConfig::from_watts(watts)
+    }
+
+    /// Forget the shadow of the configuration registers, they are read from the chip again.
+    /// Call it after the chip was reset, staged writes are dropped. The chip is locked again
+    /// by a reset, writes are not kept in the shadow until the unlock sequence is sent.
+    pub fn invalidate(&mut self) {
+        self.shadow.invalidate();
+        self.shadow.set_unlocked(false);
+    }
+
+    /// Some staged writes were not flushed yet.
+    pub fn has_staged_writes(&self) -> bool {
+        self.shadow.is_dirty()
+    }
+
+    #[inline(always)]
+    pub async fn get_buck_output_millivolts(&mut self) -> Result<Millivolts, OperationError<E>> {
+        let [high, low] = self
+            .read_live_pair(Register::BuckOutputVoltageHigh8b)
             .await?;
-        Ok(((buf[0] as u16) << 4) * 6)
+        let high = BuckOutputVoltageHigh8b::try_from(high)?;
+        let low = BuckOutputVoltageLow4b::try_from(low)?;
+
+        Ok(high.millivolts(low))
     }
 
     #[inline(always)]
-    pub async fn get_adc_output_milliamps(&mut self) -> Result<f32, E> {
-        let mut buf = [0u8; 1];
+    pub async fn get_buck_output_limit_milliamps(
+        &mut self,
+    ) -> Result<Milliamps, OperationError<E>> {
+        let limit: BuckOutputCurrentLimit = self.read_value().await?;
 
-        self.i2c
-            .write_read(ADDRESS, &[Register::AdcIoutData as u8], &mut buf)
-            .await?;
-        Ok((((buf[0] as u16) << 4) as f32) * 2.5)
+        Ok(limit.milliamps())
     }
 
     #[inline(always)]
-    pub async fn get_adc_config(&mut self) -> Result<AdcConfig, E> {
-        let mut buf = [0u8; 1];
+    pub async fn get_protocol(&mut self) -> Result<ProtocolIndicationResponse, OperationError<E>> {
+        self.read_value().await
+    }
 
-        self.i2c
-            .write_read(ADDRESS, &[Register::AdcConfig as u8], &mut buf)
-            .await?;
+    #[inline(always)]
+    pub async fn get_system_status(&mut self) -> Result<SystemStatusResponse, OperationError<E>> {
+        self.read_value().await
+    }
+
+    #[inline(always)]
+    pub async fn get_abnormal_case(&mut self) -> Result<AbnormalCaseResponse, OperationError<E>> {
+        self.read_value().await
+    }
 
-        let config = AdcConfig::try_from(buf[0] & 0x03).unwrap();
+    #[inline(always)]
+    pub async fn get_buck_force_off(&mut self) -> Result<BuckForceOffConfig, OperationError<E>> {
+        self.read_value().await
+    }
+
+    #[inline(always)]
+    pub async fn get_adc_input_millivolts(&mut self) -> Result<Millivolts, OperationError<E>> {
+        let data: AdcVinData = self.read_value().await?;
+        Ok(data.millivolts())
+    }
+
+    #[inline(always)]
+    pub async fn get_adc_output_millivolts(&mut self) -> Result<Millivolts, OperationError<E>> {
+        let data: AdcVoutData = self.read_value().await?;
+        Ok(data.millivolts())
+    }
 
-        self.adc_config = Some(config);
+    #[inline(always)]
+    pub async fn get_adc_output_milliamps(&mut self) -> Result<Milliamps, OperationError<E>> {
+        let data: AdcIoutData = self.read_value().await?;
+        Ok(data.milliamps())
+    }
+
+    /// Get the ADC data selection, `None` until a channel is selected after reset
+    #[inline(always)]
+    pub async fn get_adc_config(&mut self) -> Result<Option<AdcConfig>, OperationError<E>> {
+        let select: AdcDataSelect = self.read_value().await?;
 
-        Ok(config)
+        Ok(select.config)
     }
 
     /// Set the ADC data type
     #[inline(always)]
     pub async fn set_adc_config(&mut self, config: AdcConfig) -> Result<(), E> {
-        self.i2c
-            .write(ADDRESS, &[Register::AdcConfig as u8, config as u8])
-            .await?;
-
-        self.adc_config = Some(config);
+        self.write_register(Register::AdcConfig, config as u8).await
+    }
 
-        Ok(())
+    /// ADC data selection in the shadow, set by `set_adc_config` or read by `get_adc_config`.
+    fn adc_config(&self) -> Option<AdcConfig> {
+        let raw = self.shadow.get(Register::AdcConfig)?;
+        AdcDataSelect::try_from(raw)
+            .ok()
+            .and_then(|select| select.config)
     }
 
     /// Get the ADC value in raw format
     /// The meaning of the value representation is specified by reg 0x31
+    /// Bits below the ADC resolution of [`Sw35xx::quirks`] read 0.
     #[inline(always)]
-    pub async fn get_adc_data_raw(&mut self) -> Result<u16, E> {
-        let mut buf_h = [0u8; 1];
-        let mut buf_l = [0u8; 1];
-
-        self.i2c
-            .write_read(ADDRESS, &[Register::AdcDataHigh8b as u8], &mut buf_h)
-            .await?;
-        self.i2c
-            .write_read(ADDRESS, &[Register::AdcDataLow4b as u8], &mut buf_l)
-            .await?;
+    pub async fn get_adc_data_raw(&mut self) -> Result<u16, OperationError<E>> {
+        let [high, low] = self.read_live_pair(Register::AdcDataHigh8b).await?;
+        let high = AdcDataHigh8b::try_from(high)?;
+        let low = AdcDataLow4b::try_from(low)?;
 
-        Ok(((buf_h[0] as u16) << 4) | ((buf_l[0] & 0x0f) as u16))
+        Ok(self
+            .quirks()
+            .adc_data(((high.high as u16) << 4) | (low.low as u16)))
     }
 
-    /// Get the ADC value in millivolts or milliamps
+    /// Get the ADC value in the unit selected by `set_adc_config`
     /// The meaning of the value representation is specified by reg 0x31
     /// Returns None if the ADC config is not set through `set_adc_config`
     #[inline(always)]
-    pub async fn get_adc_data(&mut self) -> Result<Option<f32>, E> {
-        if self.adc_config.is_none() {
+    pub async fn get_adc_data(&mut self) -> Result<Option<AdcData>, OperationError<E>> {
+        let Some(config) = self.adc_config() else {
             return Ok(None);
+        };
+
+        let raw = self.get_adc_data_raw().await?;
+
+        Ok(Some(config.data(raw)))
+    }
+
+    /// Measure `channel` with the full 12-bit resolution of reg0x3B~3C.
+    /// The ADC data selection is switched first when needed, then the conversion of the new channel is awaited.
+    #[inline(always)]
+    pub async fn measure<D: DelayNs>(
+        &mut self,
+        channel: AdcConfig,
+        delay: &mut D,
+    ) -> Result<AdcData, OperationError<E>> {
+        if self.adc_config() != Some(channel) {
+            self.set_adc_config(channel).await?;
+            delay.delay_us(ADC_SETTLING_TIME_US).await;
         }
 
         let raw = self.get_adc_data_raw().await?;
 
-        let value = match self.adc_config.unwrap() {
-            AdcConfig::Vin => (raw as f32) * 10.0,
-            AdcConfig::Vout => (raw as f32) * 6.0,
-            AdcConfig::Iout => (raw as f32) * 2.5,
-        };
+        Ok(channel.data(raw))
+    }
 
-        Ok(Some(value))
+    /// Round-robin measurement, every call measures the channel after the previous one (Vin, Vout, Iout).
+    #[inline(always)]
+    pub async fn measure_next<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<AdcData, OperationError<E>> {
+        let channel = self.round_robin;
+        let data = self.measure(channel, delay).await?;
+        self.round_robin = channel.next();
+
+        Ok(data)
+    }
+
+    /// Measure Vin, Vout and Iout at full resolution.
+    #[inline(always)]
+    pub async fn measure_all<D: DelayNs>(
+        &mut self,
+        delay: &mut D,
+    ) -> Result<AdcMeasurements, OperationError<E>> {
+        let mut measurements = AdcMeasurements::default();
+        for _ in 0..3 {
+            match self.measure_next(delay).await? {
+                AdcData::Vin(vin) => measurements.vin = vin,
+                AdcData::Vout(vout) => measurements.vout = vout,
+                AdcData::Iout(iout) => measurements.iout = iout,
+            }
+        }
+
+        Ok(measurements)
     }
 
     /// Get this register indicates the power value set by Rset or reg0xA7.
-    /// Value range is 0x00 to 0x7F, units are watts
+    /// Value range is 0 to 127 W
     #[inline(always)]
-    pub async fn get_limit_watts(&mut self) -> Result<u8, E> {
-        let mut buf = [0u8; 1];
-        self.i2c
-            .write_read(ADDRESS, &[Register::PowerStatus as u8], &mut buf)
-            .await?;
-        Ok(buf[0] & 0x7f)
+    pub async fn get_limit_watts(&mut self) -> Result<Watts, OperationError<E>> {
+        let status: PowerStatus = self.read_value().await?;
+        Ok(status.watts())
     }
 
     /// get CC status
     #[inline(always)]
-    pub async fn get_cc_status(&mut self) -> Result<CcStatus, E> {
-        let mut buf = [0u8; 1];
-        self.i2c
-            .write_read(ADDRESS, &[Register::CcStatus as u8], &mut buf)
-            .await?;
-        Ok(buf[0].into())
+    pub async fn get_cc_status(&mut self) -> Result<CcStatus, OperationError<E>> {
+        self.read_value().await
     }
 
-    /// get power command request
+    /// Sample the output voltage, current and CC status into `meter`.
+    /// `now_ms` comes from a monotonic clock of the caller.
+    /// Returns the finished session when the sink was detached since the last update.
     #[inline(always)]
-    pub async fn get_power_command_request(
+    pub async fn update_meter(
         &mut self,
-    ) -> Result<PowerCommandRequest, OperationError<E>> {
-        let mut buf = [0u8; 1];
-        self.i2c
-            .write_read(ADDRESS, &[Register::PowerCommandRequest as u8], &mut buf)
-            .await
-            .map_err(|e| OperationError::I2CError(e))?;
-        buf[0].try_into().map_err(|e| OperationError::ModelError(e))
+        meter: &mut Meter,
+        now_ms: u64,
+    ) -> Result<Option<Session>, OperationError<E>> {
+        let voltage = self.get_adc_output_millivolts().await?;
+        let current = self.get_adc_output_milliamps().await?;
+        let cc_status = self.get_cc_status().await?;
+
+        Ok(meter.update(MeterSample {
+            at_ms: now_ms,
+            voltage,
+            current,
+            attached: cc_status.is_attached(),
+        }))
+    }
+
+    /// Poll the CC, system and protocol status into `tracker`.
+    /// `now_ms` comes from a monotonic clock of the caller.
+    #[inline(always)]
+    pub async fn poll_port(
+        &mut self,
+        tracker: &mut PortTracker,
+        now_ms: u64,
+    ) -> Result<Option<PortTransition>, OperationError<E>> {
+        let cc_status = self.get_cc_status().await?;
+        let system_status = self.get_system_status().await?;
+        let protocol = self.get_protocol().await?;
+
+        Ok(tracker.update(
+            PortState::from_status(cc_status, system_status, protocol),
+            now_ms,
+        ))
     }
 
-    /// set power command request
+    /// Read a snapshot and queue the changes since the previous poll into `detector`.
+    /// Snapshots whose status changed while they were read are skipped, returns `false` for them.
     #[inline(always)]
-    pub async fn set_power_command_request(
+    pub async fn poll_events<const N: usize>(
         &mut self,
-        config: PowerCommandRequest,
-    ) -> Result<(), E> {
-        self.i2c
-            .write(
-                ADDRESS,
-                &[
-                    Register::PowerCommandRequest as u8,
-                    ((config.send_enabled as u8) << 7) | config.command as u8,
-                ],
-            )
-            .await
+        detector: &mut EventDetector<N>,
+    ) -> Result<bool, OperationError<E>> {
+        let snapshot = self.read_snapshot().await?;
+        if snapshot.coherence != Coherence::Stable {
+            return Ok(false);
+        }
+
+        detector.update_snapshot(&snapshot);
+        Ok(true)
     }
 
-    /// send pd hard reset
+    /// Wait for the next event, polling every `interval_ms` while the queue of `detector` is empty.
     #[inline(always)]
-    pub async fn send_pd_hard_reset(&mut self) -> Result<(), E> {
-        self.set_power_command_request(PowerCommandRequest {
-            send_enabled: true,
-            command: PdCommand::HardReset,
-        })
-        .await
+    pub async fn next_event<const N: usize, D: DelayNs>(
+        &mut self,
+        detector: &mut EventDetector<N>,
+        delay: &mut D,
+        interval_ms: u32,
+    ) -> Result<Event, OperationError<E>> {
+        loop {
+            if let Some(event) = detector.pop() {
+                return Ok(event);
+            }
+
+            self.poll_events(detector).await?;
+            if detector.is_empty() {
+                delay.delay_ms(interval_ms).await;
+            }
+        }
     }
 
-    /// get fast charge config 6
+    /// Poll the abnormal case register into `monitor`.
+    /// `now_ms` comes from a monotonic clock of the caller.
     #[inline(always)]
-    pub async fn get_fast_charge_config_6(&mut self) -> Result<FastChargeConfig6, E> {
-        let mut buf = [0u8; 1];
-        self.i2c
-            .write_read(ADDRESS, &[Register::FastChargeConfig6 as u8], &mut buf)
-            .await?;
-        Ok(buf[0].into())
+    pub async fn poll_faults<const N: usize>(
+        &mut self,
+        monitor: &mut FaultMonitor<N>,
+        now_ms: u64,
+    ) -> Result<(), OperationError<E>> {
+        let abnormal_case = self.get_abnormal_case().await?;
+        monitor.update(abnormal_case, now_ms);
+        Ok(())
     }
 
-    /// set fast charge config 6
+    /// get power command request
     #[inline(always)]
-    pub async fn set_fast_charge_config_6(&mut self, config: FastChargeConfig6) -> Result<(), E> {
-        self.i2c
-            .write(ADDRESS, &[Register::FastChargeConfig6 as u8, config.into()])
-            .await
+    pub async fn get_power_command_request(
+        &mut self,
+    ) -> Result<PowerCommandRequest, OperationError<E>> {
+        self.read_value().await
     }
 
-    /// get fast charge config 5
+    /// get fast charge config 6
     #[inline(always)]
-    pub async fn get_fast_charge_config_5(&mut self) -> Result<FastChargeConfig5, E> {
-        let mut buf = [0u8; 1];
-        self.i2c
-            .write_read(ADDRESS, &[Register::FastChargeConfig5 as u8], &mut buf)
-            .await?;
-        Ok(buf[0].into())
+    pub async fn get_fast_charge_config_6(
+        &mut self,
+    ) -> Result<FastChargeConfig6, OperationError<E>> {
+        self.read_value().await
     }
 
-    /// set fast charge config 5
+    /// get fast charge config 5
     #[inline(always)]
-    pub async fn set_fast_charge_config_5(&mut self, config: FastChargeConfig5) -> Result<(), E> {
-        self.i2c
-            .write(ADDRESS, &[Register::FastChargeConfig5 as u8, config.into()])
-            .await
+    pub async fn get_fast_charge_config_5(
+        &mut self,
+    ) -> Result<FastChargeConfig5, OperationError<E>> {
+        self.read_value().await
     }
 
     /// get power config
     #[inline(always)]
-    pub async fn get_output_limit_watts(&mut self) -> Result<u8, E> {
-        let mut buf = [0u8; 1];
-        self.i2c
-            .write_read(ADDRESS, &[Register::PowerConfig as u8], &mut buf)
-            .await?;
+    pub async fn get_output_limit_watts(&mut self) -> Result<Watts, OperationError<E>> {
+        let config: PowerConfig = self.read_value().await?;
 
-        let raw = buf[0] & 0x3f;
-        if raw < 8 {
-            Ok(raw + 64)
-        } else {
-            Ok(raw)
-        }
+        Ok(config.to_watts())
     }
 
-    /// set power config
-    /// Value range is [12, 71].
+    /// get fast charge config 0
     #[inline(always)]
-    pub async fn set_output_limit_watts(&mut self, watts: u8) -> Result<(), E> {
-        let raw = if watts < 8 { watts - 64 } else { watts };
+    pub async fn get_fast_charge_config_0(
+        &mut self,
+    ) -> Result<FastChargeConfig0, OperationError<E>> {
+        self.read_value().await
+    }
 
-        self.i2c
-            .write(ADDRESS, &[Register::PowerConfig as u8, raw])
-            .await
+    /// get fast charge config 1
+    #[inline(always)]
+    pub async fn get_fast_charge_config_1(
+        &mut self,
+    ) -> Result<FastChargeConfig1, OperationError<E>> {
+        self.read_value().await
     }
 
-    /// get fast charge config 0
+    /// get fast charge config 2
     #[inline(always)]
-    pub async fn get_fast_charge_config_0(&mut self) -> Result<FastChargeConfig0, E> {
-        let mut buf = [0u8; 1];
-        self.i2c
-            .write_read(ADDRESS, &[Register::FastChargeConfig0 as u8], &mut buf)
-            .await?;
-        Ok(buf[0].into())
+    pub async fn get_fast_charge_config_2(
+        &mut self,
+    ) -> Result<FastChargeConfig2, OperationError<E>> {
+        self.read_value().await
     }
 
-    /// set fast charge config 0
+    /// get fast charge config 3
     #[inline(always)]
-    pub async fn set_fast_charge_config_0(&mut self, config: FastChargeConfig0) -> Result<(), E> {
-        self.i2c
-            .write(ADDRESS, &[Register::FastChargeConfig0 as u8, config.into()])
-            .await
+    pub async fn get_fast_charge_config_3(
+        &mut self,
+    ) -> Result<FastChargeConfig3, OperationError<E>> {
+        self.read_value().await
     }
 
-    /// get fast charge config 1
+    /// get fast charge config 4
     #[inline(always)]
-    pub async fn get_fast_charge_config_1(&mut self) -> Result<FastChargeConfig1, E> {
-        let mut buf = [0u8; 1];
-        self.i2c
-            .write_read(ADDRESS, &[Register::FastChargeConfig1 as u8], &mut buf)
-            .await?;
-        Ok(buf[0].into())
+    pub async fn get_fast_charge_config_4(
+        &mut self,
+    ) -> Result<FastChargeConfig4, OperationError<E>> {
+        self.read_value().await
     }
 
-    /// set fast charge config 1
+    /// get USB VID, from the shadow once both registers are in it
     #[inline(always)]
-    pub async fn set_fast_charge_config_1(&mut self, config: FastChargeConfig1) -> Result<(), E> {
-        self.i2c
-            .write(ADDRESS, &[Register::FastChargeConfig1 as u8, config.into()])
-            .await
+    pub async fn get_vid(&mut self) -> Result<u16, OperationError<E>> {
+        let shadowed = (
+            self.shadow.get(Register::VidConfig0),
+            self.shadow.get(Register::VidConfig1),
+        );
+        let buf = match shadowed {
+            (Some(low), Some(high)) => [low, high],
+            _ => {
+                let mut buf = [0u8; 2];
+                self.i2c
+                    .write_read(C::ADDRESS, &[Register::VidConfig0 as u8], &mut buf)
+                    .await?;
+                self.shadow.store(Register::VidConfig0, buf[0]);
+                self.shadow.store(Register::VidConfig1, buf[1]);
+                buf
+            }
+        };
+        let low = VidConfig0::try_from(buf[0])?;
+        let high = VidConfig1::try_from(buf[1])?;
+        Ok(((high.value as u16) << 8) | low.value as u16)
     }
 
-    /// get fast charge config 2
+    /// Read every writable configuration, shadowed registers are served from the shadow.
     #[inline(always)]
-    pub async fn get_fast_charge_config_2(&mut self) -> Result<FastChargeConfig2, E> {
-        let mut buf = [0u8; 1];
-        self.i2c
-            .write_read(ADDRESS, &[Register::FastChargeConfig2 as u8], &mut buf)
-            .await?;
-        Ok(buf[0].into())
+    pub async fn read_config(&mut self) -> Result<Sw3526Config, OperationError<E>> {
+        let buck_force_off: BuckForceOffConfig = self.read_value().await?;
+
+        Ok(Sw3526Config {
+            cc_un_driven_duration_buck_force_off: buck_force_off
+                .cc_un_driven_duration_buck_force_off,
+            fast_charge_0: self.read_value().await?,
+            fast_charge_1: self.read_value().await?,
+            fast_charge_2: self.read_value().await?,
+            fast_charge_3: self.read_value().await?,
+            fast_charge_4: self.read_value().await?,
+            fast_charge_5: self.read_value().await?,
+            fast_charge_6: self.read_value().await?,
+            output_limit: self.get_output_limit_watts().await?,
+            vid: self.get_vid().await?,
+        })
     }
 
-    /// set fast charge config 2
+    /// Read all live status registers in a single bus transaction.
+    /// Register blocks are read sequentially, relying on the address auto increment of the chip.
+    /// The status registers are read again at the end to report the coherence of the values.
     #[inline(always)]
-    pub async fn set_fast_charge_config_2(&mut self, config: FastChargeConfig2) -> Result<(), E> {
+    pub async fn read_snapshot(&mut self) -> Result<Sw3526Snapshot, OperationError<E>> {
+        let mut registers = SnapshotRegisters {
+            status: [0; 5],
+            abnormal: [0; 1],
+            adc: [0; 4],
+            power: [0; 2],
+            status_again: [0; 5],
+            power_again: [0; 2],
+        };
+
         self.i2c
-            .write(ADDRESS, &[Register::FastChargeConfig2 as u8, config.into()])
+            .transaction(
+                C::ADDRESS,
+                &mut [
+                    Operation::Write(&[Register::BuckOutputVoltageHigh8b as u8]),
+                    Operation::Read(&mut registers.status),
+                    Operation::Write(&[Register::AbnormalCase as u8]),
+                    Operation::Read(&mut registers.abnormal),
+                    Operation::Write(&[Register::AdcVinData as u8]),
+                    Operation::Read(&mut registers.adc),
+                    Operation::Write(&[Register::PowerStatus as u8]),
+                    Operation::Read(&mut registers.power),
+                    Operation::Write(&[Register::BuckOutputVoltageHigh8b as u8]),
+                    Operation::Read(&mut registers.status_again),
+                    Operation::Write(&[Register::PowerStatus as u8]),
+                    Operation::Read(&mut registers.power_again),
+                ],
+            )
+            .await?;
+
+        Ok(Sw3526Snapshot::try_from(&registers)?)
+    }
+}
+
+#[maybe_async_cfg::maybe(
+    sync(cfg(not(feature = "async")), self = "Sw35xx",),
+    async(feature = "async", keep_self)
+)]
+impl<I2C, E, C> Sw35xx<I2C, C, Unlocked>
+where
+    C: Chip,
+    E: i2c::Error,
+    I2C: I2c<Error = E>,
+{
+    /// Drop the setters.
+    /// The chip stays writable until it resets, there is no sequence to lock it again.
+    pub fn lock(self) -> Sw35xx<I2C, C, Locked> {
+        self.into_state()
+    }
+
+    /// Change `T` in the shadow only, the write is deferred to [`Sw35xx::flush`].
+    /// The register is read first if it is not in the shadow, registers without a shadow are written right away.
+    #[inline(always)]
+    pub async fn stage<T, F>(&mut self, f: F) -> Result<(), OperationError<E>>
+    where
+        T: RegisterValue,
+        F: FnOnce(&mut T),
+    {
+        let raw = self.read_register(T::REGISTER).await?;
+        let mut value = T::try_from(raw)?;
+        f(&mut value);
+        let value: u8 = value.into();
+        let value = (raw & !T::MASK) | (value & T::MASK);
+        if !self.shadow.stage(T::REGISTER, value) {
+            self.write_register(T::REGISTER, value).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the staged registers that differ from the chip, in address order.
+    #[inline(always)]
+    pub async fn flush(&mut self) -> Result<(), E> {
+        loop {
+            let Some((register, value)) = self.shadow.dirty().next() else {
+                break;
+            };
+            self.write_register(register, value).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Write a register of `C` differing from the SW3526 map, see [`ChipRegister`].
+    /// The bits outside of `T::MASK` keep the value read from the chip.
+    #[inline(always)]
+    pub async fn write_chip_value<T: ChipRegister<C>>(&mut self, value: T) -> Result<(), E> {
+        let value = value.into_raw() & T::MASK;
+        if T::MASK == u8::MAX {
+            return self.write_address(T::ADDRESS, value).await;
+        }
+
+        let current = self.read_address(T::ADDRESS).await?;
+        self.write_address(T::ADDRESS, (current & !T::MASK) | value)
             .await
     }
 
-    /// get fast charge config 3
+    /// Send the unlock sequence again, the chip locks itself when it resets.
     #[inline(always)]
-    pub async fn get_fast_charge_config_3(&mut self) -> Result<FastChargeConfig3, E> {
-        let mut buf = [0u8; 1];
-        self.i2c
-            .write_read(ADDRESS, &[Register::FastChargeConfig3 as u8], &mut buf)
+    pub async fn set_i2c_writable(&mut self) -> Result<(), E> {
+        self.send_unlock_sequence().await
+    }
+
+    #[inline(always)]
+    pub async fn set_buck_force_off(&mut self, config: BuckForceOffConfig) -> Result<(), E> {
+        self.write_value(config).await
+    }
+
+    /// modify buck force off config in place, bits outside the model are preserved
+    #[inline(always)]
+    pub async fn modify_buck_force_off<F>(&mut self, f: F) -> Result<(), OperationError<E>>
+    where
+        F: FnOnce(&mut BuckForceOffConfig),
+    {
+        self.modify_value(f).await
+    }
+
+    #[inline(always)]
+    async fn set_port_fast_charge_disabled(
+        &mut self,
+        disabled: bool,
+    ) -> Result<(), OperationError<E>> {
+        self.modify_fast_charge_config_4(|config| config.port_fast_charge_disabled = disabled)
+            .await
+    }
+
+    #[inline(always)]
+    async fn set_buck_forced_off(&mut self, force_off: bool) -> Result<(), OperationError<E>> {
+        self.modify_buck_force_off(|config| {
+            config.force_off = if force_off {
+                BuckForceOff::TurnOffOneSecond
+            } else {
+                BuckForceOff::Nothing
+            }
+        })
+        .await
+    }
+
+    /// Apply the register writes requested by a [`Recovery`].
+    /// The fast charge lock is written first, then the buck and the hard reset.
+    #[inline(always)]
+    pub async fn apply_recovery(
+        &mut self,
+        actions: RecoveryActions,
+    ) -> Result<(), OperationError<E>> {
+        if let Some(disabled) = actions.fast_charge_disabled {
+            self.set_port_fast_charge_disabled(disabled).await?;
+        }
+        if let Some(force_off) = actions.buck_force_off {
+            self.set_buck_forced_off(force_off).await?;
+        }
+        if actions.hard_reset {
+            self.send_pd_hard_reset().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Poll the abnormal case register into `recovery` and apply the requested writes.
+    /// `now_ms` comes from a monotonic clock of the caller.
+    #[inline(always)]
+    pub async fn poll_recovery(
+        &mut self,
+        recovery: &mut Recovery,
+        now_ms: u64,
+    ) -> Result<RecoveryActions, OperationError<E>> {
+        let abnormal_case = self.get_abnormal_case().await?;
+        let actions = recovery.update(abnormal_case, now_ms);
+        self.apply_recovery(actions).await?;
+        Ok(actions)
+    }
+
+    /// set power command request
+    #[inline(always)]
+    pub async fn set_power_command_request(
+        &mut self,
+        config: PowerCommandRequest,
+    ) -> Result<(), E> {
+        self.write_register(Register::PowerCommandRequest, config.into())
+            .await
+    }
+
+    /// send pd hard reset
+    #[inline(always)]
+    pub async fn send_pd_hard_reset(&mut self) -> Result<(), E> {
+        self.set_power_command_request(PowerCommandRequest {
+            send_enabled: true,
+            command: PdCommand::HardReset,
+        })
+        .await
+    }
+
+    /// set fast charge config 6
+    #[inline(always)]
+    pub async fn set_fast_charge_config_6(&mut self, config: FastChargeConfig6) -> Result<(), E> {
+        self.write_value(config).await
+    }
+
+    /// modify fast charge config 6 in place, bits outside the model are preserved
+    #[inline(always)]
+    pub async fn modify_fast_charge_config_6<F>(&mut self, f: F) -> Result<(), OperationError<E>>
+    where
+        F: FnOnce(&mut FastChargeConfig6),
+    {
+        self.modify_value(f).await
+    }
+
+    /// set fast charge config 5
+    #[inline(always)]
+    pub async fn set_fast_charge_config_5(&mut self, config: FastChargeConfig5) -> Result<(), E> {
+        self.write_value(config).await
+    }
+
+    /// modify fast charge config 5 in place, bits outside the model are preserved
+    #[inline(always)]
+    pub async fn modify_fast_charge_config_5<F>(&mut self, f: F) -> Result<(), OperationError<E>>
+    where
+        F: FnOnce(&mut FastChargeConfig5),
+    {
+        self.modify_value(f).await
+    }
+
+    /// set power config
+    /// Value range is given by [`Sw35xx::capabilities`], [12, 71] for the SW3526,
+    /// returns `ModelError::OutOfRange` otherwise.
+    #[inline(always)]
+    pub async fn set_output_limit_watts(&mut self, watts: Watts) -> Result<(), OperationError<E>> {
+        let config = self.output_limit_config(watts)?;
+
+        self.write_value(config).await?;
+
+        Ok(())
+    }
+
+    /// Set the output power from reg0xA7 instead of the Rset resistance, if not already,
+    /// as `set_output_limit_watts` has no effect otherwise. Write reg0xA7 first.
+    #[inline(always)]
+    async fn use_register_power_setting(&mut self) -> Result<(), OperationError<E>> {
+        let config: FastChargeConfig3 = self.read_value().await?;
+        if config.power_setting_method != PowerSettingMethod::Register {
+            self.write_value(FastChargeConfig3 {
+                power_setting_method: PowerSettingMethod::Register,
+                ..config
+            })
             .await?;
-        Ok(buf[0].into())
+        }
+
+        Ok(())
+    }
+
+    /// Poll the abnormal case register into `derating` and apply the requested limit.
+    /// The output power is switched to be set by reg0xA7 on the first change, once reg0xA7 is written.
+    /// `now_ms` comes from a monotonic clock of the caller.
+    #[inline(always)]
+    pub async fn poll_derating(
+        &mut self,
+        derating: &mut Derating,
+        now_ms: u64,
+    ) -> Result<Option<DeratingEvent>, OperationError<E>> {
+        let abnormal_case = self.get_abnormal_case().await?;
+        let event = derating.update(abnormal_case, now_ms);
+        if let Some(event) = event {
+            self.set_output_limit_watts(event.limit()).await?;
+            self.use_register_power_setting().await?;
+        }
+
+        Ok(event)
+    }
+
+    /// Read the state of every port and apply the limits computed by `allocator`.
+    /// Ports whose limit goes down are written before any goes up,
+    /// so the sum of the limits stays within the budget during the transition.
+    /// The output power of a port is switched to be set by reg0xA7 after its first write,
+    /// so the port never runs on a stale reg0xA7.
+    /// Every port sits on its own bus, as they share the same address.
+    pub async fn allocate_power<const N: usize>(
+        ports: &mut [Self; N],
+        allocator: &mut PowerAllocator<N>,
+    ) -> Result<Allocation<N>, OperationError<E>> {
+        let mut readings = [PortReading::DETACHED; N];
+        for (port, reading) in ports.iter_mut().zip(readings.iter_mut()) {
+            let voltage = port.get_adc_output_millivolts().await?;
+            let current = port.get_adc_output_milliamps().await?;
+            *reading = PortReading {
+                attached: port.get_cc_status().await?.is_attached(),
+                protocol: port.get_protocol().await?,
+                power: voltage.checked_power(current).unwrap_or(Milliwatts::MAX),
+                limit: port.get_limit_watts().await?,
+            };
+        }
+
+        let limits = allocator.allocate(&readings);
+
+        for raise in [false, true] {
+            for ((port, reading), limit) in ports.iter_mut().zip(&readings).zip(limits) {
+                if limit != reading.limit && (limit > reading.limit) == raise {
+                    port.set_output_limit_watts(limit).await?;
+                    port.use_register_power_setting().await?;
+                }
+            }
+        }
+
+        Ok(Allocation { readings, limits })
+    }
+
+    /// Apply the register writes requested by a [`Foldback`].
+    /// Power is reduced before the buck is forced off, the output power is switched to be set by reg0xA7
+    /// once it is written.
+    #[inline(always)]
+    pub async fn apply_foldback(
+        &mut self,
+        actions: FoldbackActions,
+    ) -> Result<(), OperationError<E>> {
+        if let Some(limit) = actions.limit {
+            self.set_output_limit_watts(limit).await?;
+            self.use_register_power_setting().await?;
+        }
+        if let Some(disabled) = actions.fast_charge_disabled {
+            self.set_port_fast_charge_disabled(disabled).await?;
+        }
+        if let Some(force_off) = actions.buck_force_off {
+            self.set_buck_forced_off(force_off).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Measure VIN into `foldback` and apply the requested writes.
+    #[inline(always)]
+    pub async fn poll_foldback(
+        &mut self,
+        foldback: &mut Foldback,
+    ) -> Result<FoldbackActions, OperationError<E>> {
+        let vin = self.get_adc_input_millivolts().await?;
+        let actions = foldback.update(vin);
+        self.apply_foldback(actions).await?;
+        Ok(actions)
+    }
+
+    /// set fast charge config 0
+    #[inline(always)]
+    pub async fn set_fast_charge_config_0(&mut self, config: FastChargeConfig0) -> Result<(), E> {
+        self.write_value(config).await
+    }
+
+    /// modify fast charge config 0 in place, bits outside the model are preserved
+    #[inline(always)]
+    pub async fn modify_fast_charge_config_0<F>(&mut self, f: F) -> Result<(), OperationError<E>>
+    where
+        F: FnOnce(&mut FastChargeConfig0),
+    {
+        self.modify_value(f).await
+    }
+
+    /// set fast charge config 1
+    #[inline(always)]
+    pub async fn set_fast_charge_config_1(&mut self, config: FastChargeConfig1) -> Result<(), E> {
+        self.write_value(config).await
+    }
+
+    /// modify fast charge config 1 in place, bits outside the model are preserved
+    #[inline(always)]
+    pub async fn modify_fast_charge_config_1<F>(&mut self, f: F) -> Result<(), OperationError<E>>
+    where
+        F: FnOnce(&mut FastChargeConfig1),
+    {
+        self.modify_value(f).await
+    }
+
+    /// set fast charge config 2
+    #[inline(always)]
+    pub async fn set_fast_charge_config_2(&mut self, config: FastChargeConfig2) -> Result<(), E> {
+        self.write_value(config).await
+    }
+
+    /// modify fast charge config 2 in place, bits outside the model are preserved
+    #[inline(always)]
+    pub async fn modify_fast_charge_config_2<F>(&mut self, f: F) -> Result<(), OperationError<E>>
+    where
+        F: FnOnce(&mut FastChargeConfig2),
+    {
+        self.modify_value(f).await
     }
 
     /// set fast charge config 3
     #[inline(always)]
     pub async fn set_fast_charge_config_3(&mut self, config: FastChargeConfig3) -> Result<(), E> {
-        self.i2c
-            .write(ADDRESS, &[Register::FastChargeConfig3 as u8, config.into()])
-            .await
+        self.write_value(config).await
     }
 
-    /// get fast charge config 4
+    /// modify fast charge config 3 in place, bits outside the model are preserved
     #[inline(always)]
-    pub async fn get_fast_charge_config_4(&mut self) -> Result<FastChargeConfig4, E> {
-        let mut buf = [0u8; 1];
-        self.i2c
-            .write_read(ADDRESS, &[Register::FastChargeConfig4 as u8], &mut buf)
-            .await?;
-        Ok(buf[0].into())
+    pub async fn modify_fast_charge_config_3<F>(&mut self, f: F) -> Result<(), OperationError<E>>
+    where
+        F: FnOnce(&mut FastChargeConfig3),
+    {
+        self.modify_value(f).await
     }
 
     /// set fast charge config 4
     #[inline(always)]
     pub async fn set_fast_charge_config_4(&mut self, config: FastChargeConfig4) -> Result<(), E> {
-        self.i2c
-            .write(ADDRESS, &[Register::FastChargeConfig4 as u8, config.into()])
-            .await
+        self.write_value(config).await
     }
 
-    /// get USB VID
+    /// modify fast charge config 4 in place, bits outside the model are preserved
     #[inline(always)]
-    pub async fn get_vid(&mut self) -> Result<u16, E> {
-        let mut buf_l = [0u8; 1];
-        let mut buf_h = [0u8; 1];
-        self.i2c
-            .write_read(ADDRESS, &[Register::VidConfig0 as u8], &mut buf_l)
-            .await?;
-        self.i2c
-            .write_read(ADDRESS, &[Register::VidConfig1 as u8], &mut buf_h)
-            .await?;
-        Ok(((buf_h[0] as u16) << 8) | buf_l[0] as u16)
+    pub async fn modify_fast_charge_config_4<F>(&mut self, f: F) -> Result<(), OperationError<E>>
+    where
+        F: FnOnce(&mut FastChargeConfig4),
+    {
+        self.modify_value(f).await
     }
 
     /// set USB VID
     #[inline(always)]
     pub async fn set_vid(&mut self, vid: u16) -> Result<(), E> {
-        self.i2c
-            .write(ADDRESS, &[Register::VidConfig0 as u8, (vid >> 8) as u8])
-            .await?;
-        self.i2c
-            .write(ADDRESS, &[Register::VidConfig1 as u8, vid as u8])
-            .await
+        self.write_value(VidConfig0 { value: vid as u8 }).await?;
+        self.write_value(VidConfig1 {
+            value: (vid >> 8) as u8,
+        })
+        .await
+    }
+
+    /// Write `config` and read it back from the chip.
+    /// The unlock sequence is sent first in case the chip was reset.
+    /// Returns the fields that did not take, empty if the chip matches `config`.
+    /// `config` is checked before anything is written, see [`Sw35xx::set_output_limit_watts`].
+    #[inline(always)]
+    pub async fn apply_config(
+        &mut self,
+        config: &Sw3526Config,
+    ) -> Result<ConfigDiff, OperationError<E>> {
+        let output_limit = self.output_limit_config(config.output_limit)?;
+
+        self.send_unlock_sequence().await?;
+        self.write_value(BuckForceOffConfig {
+            force_off: BuckForceOff::Nothing,
+            cc_un_driven_duration_buck_force_off: config.cc_un_driven_duration_buck_force_off,
+        })
+        .await?;
+        self.write_value(config.fast_charge_6).await?;
+        self.write_value(config.fast_charge_5).await?;
+        self.write_value(output_limit).await?;
+        self.write_value(config.fast_charge_0).await?;
+        self.write_value(config.fast_charge_1).await?;
+        self.write_value(config.fast_charge_2).await?;
+        self.write_value(config.fast_charge_3).await?;
+        self.write_value(config.fast_charge_4).await?;
+        self.set_vid(config.vid).await?;
+
+        self.shadow.invalidate();
+        let actual = self.read_config().await?;
+
+        Ok(config.diff(&actual))
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_comparison)]
 mod tests {
     use super::*;
+    use embedded_hal_mock::eh1::delay::{CheckedDelay, Transaction as DelayTransaction};
     use embedded_hal_mock::eh1::i2c::{Mock, Transaction};
 
+    const ADDRESS: u8 = Sw3526::ADDRESS;
+
     #[test]
     fn get_chip_version() {
         let i2c_expectations = [Transaction::write_read(ADDRESS, vec![0x01], vec![0x01])];
@@ -496,18 +1282,184 @@ mod tests {
         i2c.done();
     }
 
+    #[test]
+    fn probe() {
+        let i2c_expectations = [
+            Transaction::write_read(ADDRESS, vec![0x01], vec![0x02]),
+            Transaction::write_read(ADDRESS, vec![0x3a], vec![0x01]),
+            Transaction::write(ADDRESS, vec![0x3a, 0x02]),
+            Transaction::write_read(ADDRESS, vec![0x3a], vec![0x02]),
+            Transaction::write(ADDRESS, vec![0x3a, 0x01]),
+        ];
+        let mut i2c = Mock::new(&i2c_expectations);
+        let mut sw3526 = SW3526::new(i2c.clone());
+
+        assert!(sw3526.chip_info().is_none());
+        let info = sw3526.probe().unwrap();
+
+        assert!(info.revision == ChipRevision::V2);
+        let sw3526 = sw3526.assume_unlocked();
+        assert!(sw3526.chip_info() == Some(&info));
+        assert!(sw3526.capabilities().max_power == Watts(71));
+
+        i2c.done();
+    }
+
+    #[test]
+    fn probe_quirks() {
+        /// SW3526 whose V2 revision has a 10-bit ADC
+        struct TenBitAdc;
+
+        impl Chip for TenBitAdc {
+            const NAME: &'static str = "SW3526";
+            const ADDRESS: u8 = ADDRESS;
+            const CAPABILITIES: Capabilities = Sw3526::CAPABILITIES;
+            const ERRATA: Errata = [
+                Quirks::NONE,
+                Quirks::NONE,
+                Quirks { adc_bits: 10 },
+                Quirks::NONE,
+            ];
+        }
+
+        let i2c_expectations = [
+            Transaction::transaction_start(ADDRESS),
+            Transaction::write(ADDRESS, vec![0x3b]),
+            Transaction::read(ADDRESS, vec![0xff, 0xff]),
+            Transaction::write(ADDRESS, vec![0x3b]),
+            Transaction::read(ADDRESS, vec![0xff]),
+            Transaction::transaction_end(ADDRESS),
+            Transaction::write_read(ADDRESS, vec![0x01], vec![0x02]),
+            Transaction::write_read(ADDRESS, vec![0x3a], vec![0x01]),
+            Transaction::write(ADDRESS, vec![0x3a, 0x02]),
+            Transaction::write_read(ADDRESS, vec![0x3a], vec![0x02]),
+            Transaction::write(ADDRESS, vec![0x3a, 0x01]),
+            Transaction::transaction_start(ADDRESS),
+            Transaction::write(ADDRESS, vec![0x3b]),
+            Transaction::read(ADDRESS, vec![0xff, 0xff]),
+            Transaction::write(ADDRESS, vec![0x3b]),
+            Transaction::read(ADDRESS, vec![0xff]),
+            Transaction::transaction_end(ADDRESS),
+        ];
+        let mut i2c = Mock::new(&i2c_expectations);
+        let mut chip = Sw35xx::<_, TenBitAdc>::new(i2c.clone());
+
+        // the revision is not known before the probe
+        assert!(chip.get_adc_data_raw().unwrap() == 0xfff);
+        assert!(chip.probe().unwrap().quirks.adc_bits == 10);
+        assert!(chip.get_adc_data_raw().unwrap() == 0xffc);
+
+        i2c.done();
+    }
+
+    #[test]
+    fn probe_other_device() {
+        let i2c_expectations = [
+            Transaction::write_read(ADDRESS, vec![0x01], vec![0xff]),
+            Transaction::write_read(ADDRESS, vec![0x3a], vec![0xff]),
+            Transaction::write(ADDRESS, vec![0x3a, 0xfd]),
+            Transaction::write_read(ADDRESS, vec![0x3a], vec![0xff]),
+            Transaction::write(ADDRESS, vec![0x3a, 0xff]),
+        ];
+        let mut i2c = Mock::new(&i2c_expectations);
+        let mut sw3526 = SW3526::new(i2c.clone());
+
+        assert!(matches!(
+            sw3526.probe(),
+            Err(OperationError::ModelError(ModelError::UnknownDevice {
+                register: Register::AdcConfig,
+            }))
+        ));
+        assert!(sw3526.chip_info().is_none());
+
+        i2c.done();
+    }
+
+    #[test]
+    fn probe_no_answer() {
+        let i2c_expectations = [Transaction::write_read(ADDRESS, vec![0x01], vec![0x00])
+            .with_error(embedded_hal::i2c::ErrorKind::NoAcknowledge(
+                embedded_hal::i2c::NoAcknowledgeSource::Address,
+            ))];
+        let mut i2c = Mock::new(&i2c_expectations);
+        let mut sw3526 = SW3526::new(i2c.clone());
+
+        assert!(matches!(sw3526.probe(), Err(OperationError::I2CError(_))));
+        assert!(sw3526.chip_info().is_none());
+
+        i2c.done();
+    }
+
     #[test]
     fn get_buck_output_millivolts() {
         let i2c_expectations = [
-            Transaction::write_read(ADDRESS, vec![0x03], vec![0xff]), // eq 0xff
-            Transaction::write_read(ADDRESS, vec![0x04], vec![0xff]),
+            Transaction::transaction_start(ADDRESS),
+            Transaction::write(ADDRESS, vec![0x03]),
+            Transaction::read(ADDRESS, vec![0xff, 0xff]), // eq 0xff, 0xf0
+            Transaction::write(ADDRESS, vec![0x03]),
+            Transaction::read(ADDRESS, vec![0xff]),
+            Transaction::transaction_end(ADDRESS),
         ]; // eq 0xf0
         let mut i2c = Mock::new(&i2c_expectations);
         let mut sw3526 = SW3526::new(i2c.clone());
 
         let output = sw3526.get_buck_output_millivolts().unwrap();
 
-        assert!(output == 40950);
+        assert!(output == Millivolts(40950));
+
+        i2c.done();
+    }
+
+    #[test]
+    fn get_buck_output_millivolts_rollover() {
+        let i2c_expectations = [
+            Transaction::transaction_start(ADDRESS),
+            Transaction::write(ADDRESS, vec![0x03]),
+            Transaction::read(ADDRESS, vec![0x4a, 0x00]), // low byte already rolled over
+            Transaction::write(ADDRESS, vec![0x03]),
+            Transaction::read(ADDRESS, vec![0x4b]),
+            Transaction::transaction_end(ADDRESS),
+            Transaction::transaction_start(ADDRESS),
+            Transaction::write(ADDRESS, vec![0x03]),
+            Transaction::read(ADDRESS, vec![0x4b, 0x00]),
+            Transaction::write(ADDRESS, vec![0x03]),
+            Transaction::read(ADDRESS, vec![0x4b]),
+            Transaction::transaction_end(ADDRESS),
+        ];
+        let mut i2c = Mock::new(&i2c_expectations);
+        let mut sw3526 = SW3526::new(i2c.clone());
+
+        let output = sw3526.get_buck_output_millivolts().unwrap();
+
+        assert!(output == Millivolts(12000));
+
+        i2c.done();
+    }
+
+    #[test]
+    fn get_adc_data_raw_unstable() {
+        let mut i2c_expectations = vec![];
+        for high in [0x10, 0x20, 0x30] {
+            i2c_expectations.extend([
+                Transaction::transaction_start(ADDRESS),
+                Transaction::write(ADDRESS, vec![0x3b]),
+                Transaction::read(ADDRESS, vec![high, 0x00]),
+                Transaction::write(ADDRESS, vec![0x3b]),
+                Transaction::read(ADDRESS, vec![high + 1]),
+                Transaction::transaction_end(ADDRESS),
+            ]);
+        }
+        let mut i2c = Mock::new(&i2c_expectations);
+        let mut sw3526 = SW3526::new(i2c.clone());
+
+        let err = sw3526.get_adc_data_raw().unwrap_err();
+
+        assert!(matches!(
+            err,
+            OperationError::ModelError(ModelError::Unstable {
+                register: Register::AdcDataHigh8b
+            })
+        ));
 
         i2c.done();
     }
@@ -520,7 +1472,7 @@ mod tests {
 
         let limit = sw3526.get_buck_output_limit_milliamps().unwrap();
 
-        assert!(limit == 4150);
+        assert!(limit == Milliamps(4150));
 
         i2c.done();
     }
@@ -615,13 +1567,83 @@ mod tests {
             Transaction::write(ADDRESS, vec![0x12, 0x80]),
         ];
         let mut i2c = Mock::new(&i2c_expectations);
-        let mut sw3526 = SW3526::new(i2c.clone());
+        let mut sw3526 = SW3526::new(i2c.clone()).assume_unlocked();
 
         sw3526.set_i2c_writable().unwrap();
 
         i2c.done();
     }
 
+    #[test]
+    fn unlock() {
+        let i2c_expectations = [
+            Transaction::write(ADDRESS, vec![0x12, 0x20]),
+            Transaction::write(ADDRESS, vec![0x12, 0x40]),
+            Transaction::write(ADDRESS, vec![0x12, 0x80]),
+            Transaction::write(ADDRESS, vec![0x70, 0x81]),
+        ];
+        let mut i2c = Mock::new(&i2c_expectations);
+        let sw3526 = SW3526::new(i2c.clone());
+
+        let mut sw3526 = sw3526.unlock().unwrap();
+        sw3526.send_pd_hard_reset().unwrap();
+        let _locked: SW3526<_, Locked> = sw3526.lock();
+
+        i2c.done();
+    }
+
+    #[test]
+    fn is_writable() {
+        let i2c_expectations = [
+            Transaction::write_read(ADDRESS, vec![0x70], vec![0x01]),
+            Transaction::write(ADDRESS, vec![0x70, 0x00]),
+            Transaction::write_read(ADDRESS, vec![0x70], vec![0x00]),
+            Transaction::write(ADDRESS, vec![0x70, 0x01]),
+        ];
+        let mut i2c = Mock::new(&i2c_expectations);
+        let sw3526 = SW3526::new(i2c.clone());
+
+        assert!(sw3526.into_unlocked_if_writable().unwrap().is_ok());
+
+        i2c.done();
+    }
+
+    #[test]
+    fn is_writable_locked() {
+        let i2c_expectations = [
+            Transaction::write_read(ADDRESS, vec![0x70], vec![0x00]),
+            Transaction::write(ADDRESS, vec![0x70, 0x01]),
+            Transaction::write_read(ADDRESS, vec![0x70], vec![0x00]),
+            Transaction::write(ADDRESS, vec![0x70, 0x00]),
+        ];
+        let mut i2c = Mock::new(&i2c_expectations);
+        let mut sw3526 = SW3526::new(i2c.clone());
+
+        assert!(!sw3526.is_writable().unwrap());
+
+        i2c.done();
+    }
+
+    #[test]
+    fn is_writable_restores_on_error() {
+        let i2c_expectations = [
+            Transaction::write_read(ADDRESS, vec![0x70], vec![0x02]),
+            Transaction::write(ADDRESS, vec![0x70, 0x03]),
+            Transaction::write_read(ADDRESS, vec![0x70], vec![0x00])
+                .with_error(embedded_hal::i2c::ErrorKind::Bus),
+            Transaction::write(ADDRESS, vec![0x70, 0x02]),
+        ];
+        let mut i2c = Mock::new(&i2c_expectations);
+        let mut sw3526 = SW3526::new(i2c.clone());
+
+        assert!(matches!(
+            sw3526.is_writable(),
+            Err(OperationError::I2CError(_))
+        ));
+
+        i2c.done();
+    }
+
     #[test]
     fn get_buck_force_off() {
         let i2c_expectations = [Transaction::write_read(ADDRESS, vec![0x13], vec![0x80])];
@@ -644,141 +1666,382 @@ mod tests {
 
     #[test]
     fn set_buck_force_off() {
-        let i2c_expectations = [Transaction::write(ADDRESS, vec![0x13, 0xc0])];
+        let i2c_expectations = [
+            Transaction::write_read(ADDRESS, vec![0x13], vec![0x3f]),
+            Transaction::write(ADDRESS, vec![0x13, 0xff]),
+        ];
+        let mut i2c = Mock::new(&i2c_expectations);
+
+        let mut sw3526 = SW3526::new(i2c.clone()).assume_unlocked();
+        sw3526
+            .set_buck_force_off(BuckForceOffConfig {
+                force_off: BuckForceOff::TurnOffOneSecond,
+                cc_un_driven_duration_buck_force_off: CCUnDrivenDurationBuckForceOff::UnDriven,
+            })
+            .unwrap();
+
+        i2c.done();
+    }
+
+    #[test]
+    fn modify_buck_force_off() {
+        let i2c_expectations = [
+            Transaction::write_read(ADDRESS, vec![0x13], vec![0x15]),
+            Transaction::write(ADDRESS, vec![0x13, 0x95]),
+        ];
+        let mut i2c = Mock::new(&i2c_expectations);
+        let mut sw3526 = SW3526::new(i2c.clone()).assume_unlocked();
+
+        sw3526
+            .modify_buck_force_off(|config| config.force_off = BuckForceOff::TurnOffOneSecond)
+            .unwrap();
+
+        i2c.done();
+    }
+
+    #[test]
+    fn get_adc_input_millivolts() {
+        let i2c_expectations = [Transaction::write_read(ADDRESS, vec![0x30], vec![0xff])];
+        let mut i2c = Mock::new(&i2c_expectations);
+        let mut sw3526 = SW3526::new(i2c.clone());
+
+        let input = sw3526.get_adc_input_millivolts().unwrap();
+
+        assert!(input == Millivolts(40800));
+
+        i2c.done();
+    }
+
+    #[test]
+    fn get_adc_output_millivolts() {
+        let i2c_expectations = [Transaction::write_read(ADDRESS, vec![0x31], vec![0xff])];
+        let mut i2c = Mock::new(&i2c_expectations);
+        let mut sw3526 = SW3526::new(i2c.clone());
+
+        let output = sw3526.get_adc_output_millivolts().unwrap();
+
+        assert!(output == Millivolts(24480));
+
+        i2c.done();
+    }
+
+    #[test]
+    fn get_adc_output_milliamps() {
+        let i2c_expectations = [Transaction::write_read(ADDRESS, vec![0x33], vec![0xff])];
+        let mut i2c = Mock::new(&i2c_expectations);
+        let mut sw3526 = SW3526::new(i2c.clone());
+
+        let output = sw3526.get_adc_output_milliamps().unwrap();
+
+        assert!(output == Milliamps(10200));
+
+        i2c.done();
+    }
+
+    #[test]
+    fn get_adc_config() {
+        let i2c_expectations = [Transaction::write_read(ADDRESS, vec![0x3a], vec![0xff])]; // eq 0x3
+        let mut i2c = Mock::new(&i2c_expectations);
+        let mut sw3526 = SW3526::new(i2c.clone());
+
+        let config = sw3526.get_adc_config().unwrap();
+
+        assert!(matches!(config, Some(AdcConfig::Iout)));
+
+        i2c.done();
+    }
+
+    #[test]
+    fn get_adc_config_after_reset() {
+        let i2c_expectations = [Transaction::write_read(ADDRESS, vec![0x3a], vec![0x00])];
+        let mut i2c = Mock::new(&i2c_expectations);
+        let mut sw3526 = SW3526::new(i2c.clone());
+
+        let config = sw3526.get_adc_config().unwrap();
+
+        assert!(config.is_none());
+
+        i2c.done();
+    }
+
+    #[test]
+    fn set_adc_config() {
+        let i2c_expectations = [Transaction::write(ADDRESS, vec![0x3a, 0x01])];
+        let mut i2c = Mock::new(&i2c_expectations);
+
+        let mut sw3526 = SW3526::new(i2c.clone());
+        sw3526.set_adc_config(AdcConfig::Vin).unwrap();
+
+        i2c.done();
+    }
+
+    #[test]
+    fn get_adc_data_raw() {
+        let i2c_expectations = [
+            Transaction::transaction_start(ADDRESS),
+            Transaction::write(ADDRESS, vec![0x3b]),
+            Transaction::read(ADDRESS, vec![0xff, 0xff]), // eq 0xff, 0x0f
+            Transaction::write(ADDRESS, vec![0x3b]),
+            Transaction::read(ADDRESS, vec![0xff]),
+            Transaction::transaction_end(ADDRESS),
+        ];
+        let mut i2c = Mock::new(&i2c_expectations);
+        let mut sw3526 = SW3526::new(i2c.clone());
+
+        let data = sw3526.get_adc_data_raw().unwrap();
+
+        assert!(data == 4095);
+
+        i2c.done();
+    }
+
+    #[test]
+    fn get_adc_data_when_adc_config_not_configured() {
+        let i2c_expectations = [];
         let mut i2c = Mock::new(&i2c_expectations);
+        let mut sw3526 = SW3526::new(i2c.clone());
+
+        let data = sw3526.get_adc_data().unwrap();
+
+        assert!(data.is_none());
+
+        i2c.done();
+    }
+
+    #[test]
+    fn get_adc_data_when_adc_config_configured() {
+        let i2c_expectations = [
+            Transaction::write(ADDRESS, vec![0x3a, 0x01]),
+            Transaction::transaction_start(ADDRESS),
+            Transaction::write(ADDRESS, vec![0x3b]),
+            Transaction::read(ADDRESS, vec![0xff, 0xff]), // eq 0xff, 0x0f
+            Transaction::write(ADDRESS, vec![0x3b]),
+            Transaction::read(ADDRESS, vec![0xff]),
+            Transaction::transaction_end(ADDRESS),
+        ];
+        let mut i2c = Mock::new(&i2c_expectations);
+        let mut sw3526 = SW3526::new(i2c.clone());
+
+        sw3526.set_adc_config(AdcConfig::Vin).unwrap();
+        let data = sw3526.get_adc_data().unwrap();
 
-        let mut sw3526 = SW3526::new(i2c.clone());
-        sw3526
-            .set_buck_force_off(BuckForceOffConfig {
-                force_off: BuckForceOff::TurnOffOneSecond,
-                cc_un_driven_duration_buck_force_off: CCUnDrivenDurationBuckForceOff::UnDriven,
-            })
-            .unwrap();
+        assert!(data == Some(AdcData::Vin(Millivolts(40950))));
 
         i2c.done();
     }
 
     #[test]
-    fn get_adc_input_millivolts() {
-        let i2c_expectations = [Transaction::write_read(ADDRESS, vec![0x30], vec![0xff])];
+    fn get_adc_data_iout() {
+        let i2c_expectations = [
+            Transaction::write(ADDRESS, vec![0x3a, 0x03]),
+            Transaction::transaction_start(ADDRESS),
+            Transaction::write(ADDRESS, vec![0x3b]),
+            Transaction::read(ADDRESS, vec![0x00, 0x03]),
+            Transaction::write(ADDRESS, vec![0x3b]),
+            Transaction::read(ADDRESS, vec![0x00]),
+            Transaction::transaction_end(ADDRESS),
+        ];
         let mut i2c = Mock::new(&i2c_expectations);
         let mut sw3526 = SW3526::new(i2c.clone());
 
-        let input = sw3526.get_adc_input_millivolts().unwrap();
+        sw3526.set_adc_config(AdcConfig::Iout).unwrap();
+        let data = sw3526.get_adc_data().unwrap();
 
-        assert!(input == 40800);
+        assert!(data == Some(AdcData::Iout(Microamps(7500))));
 
         i2c.done();
     }
 
+    fn adc_data_expectations(high: u8, low: u8) -> [Transaction; 6] {
+        [
+            Transaction::transaction_start(ADDRESS),
+            Transaction::write(ADDRESS, vec![0x3b]),
+            Transaction::read(ADDRESS, vec![high, low]),
+            Transaction::write(ADDRESS, vec![0x3b]),
+            Transaction::read(ADDRESS, vec![high]),
+            Transaction::transaction_end(ADDRESS),
+        ]
+    }
+
     #[test]
-    fn get_adc_output_millivolts() {
-        let i2c_expectations = [Transaction::write_read(ADDRESS, vec![0x31], vec![0xff])];
+    fn measure() {
+        let mut i2c_expectations = vec![Transaction::write(ADDRESS, vec![0x3a, 0x02])];
+        i2c_expectations.extend(adc_data_expectations(0x7d, 0x00));
+        i2c_expectations.extend(adc_data_expectations(0x7d, 0x01));
         let mut i2c = Mock::new(&i2c_expectations);
+        let mut delay = CheckedDelay::new(&[DelayTransaction::delay_us(ADC_SETTLING_TIME_US)]);
         let mut sw3526 = SW3526::new(i2c.clone());
 
-        let output = sw3526.get_adc_output_millivolts().unwrap();
+        let first = sw3526.measure(AdcConfig::Vout, &mut delay).unwrap();
+        // already selected, no switch and no wait
+        let second = sw3526.measure(AdcConfig::Vout, &mut delay).unwrap();
 
-        assert!(output == 24480);
+        assert!(first == AdcData::Vout(Millivolts(12000)));
+        assert!(second == AdcData::Vout(Millivolts(12006)));
 
         i2c.done();
+        delay.done();
     }
 
     #[test]
-    fn get_adc_output_milliamps() {
-        let i2c_expectations = [Transaction::write_read(ADDRESS, vec![0x33], vec![0xff])];
+    fn measure_all() {
+        let mut i2c_expectations = vec![];
+        for (channel, high, low) in [(0x01, 0x7d, 0x00), (0x02, 0xc8, 0x00), (0x03, 0x4b, 0x01)] {
+            i2c_expectations.push(Transaction::write(ADDRESS, vec![0x3a, channel]));
+            i2c_expectations.extend(adc_data_expectations(high, low));
+        }
         let mut i2c = Mock::new(&i2c_expectations);
+        let mut delay =
+            CheckedDelay::new(&vec![DelayTransaction::delay_us(ADC_SETTLING_TIME_US); 3]);
         let mut sw3526 = SW3526::new(i2c.clone());
 
-        let output = sw3526.get_adc_output_milliamps().unwrap();
+        let measurements = sw3526.measure_all(&mut delay).unwrap();
 
-        assert!(output == 10200f32);
+        assert!(measurements.vin == Millivolts(20000));
+        assert!(measurements.vout == Millivolts(19200));
+        assert!(measurements.iout == Microamps(3_002_500));
 
         i2c.done();
+        delay.done();
     }
 
     #[test]
-    fn get_adc_config() {
-        let i2c_expectations = [Transaction::write_read(ADDRESS, vec![0x3a], vec![0xff])]; // eq 0x3
+    fn get_cc_status() {
+        let i2c_expectations = [Transaction::write_read(ADDRESS, vec![0x69], vec![0xff])]; // eq 0x30
         let mut i2c = Mock::new(&i2c_expectations);
         let mut sw3526 = SW3526::new(i2c.clone());
 
-        let config = sw3526.get_adc_config().unwrap();
+        let status = sw3526.get_cc_status().unwrap();
 
-        assert!(matches!(config, AdcConfig::Iout));
+        assert!(status.cc1_attached == true);
+        assert!(status.cc2_attached == true);
 
         i2c.done();
     }
 
     #[test]
-    fn set_adc_config() {
-        let i2c_expectations = [Transaction::write(ADDRESS, vec![0x3a, 0x01])];
+    fn update_meter() {
+        let i2c_expectations = [
+            Transaction::write_read(ADDRESS, vec![0x31], vec![0x5d]), // 8928 mV
+            Transaction::write_read(ADDRESS, vec![0x33], vec![0x32]), // 2000 mA
+            Transaction::write_read(ADDRESS, vec![0x69], vec![0x80]),
+            Transaction::write_read(ADDRESS, vec![0x31], vec![0x5d]),
+            Transaction::write_read(ADDRESS, vec![0x33], vec![0x32]),
+            Transaction::write_read(ADDRESS, vec![0x69], vec![0x80]),
+            Transaction::write_read(ADDRESS, vec![0x31], vec![0x00]),
+            Transaction::write_read(ADDRESS, vec![0x33], vec![0x00]),
+            Transaction::write_read(ADDRESS, vec![0x69], vec![0x00]),
+        ];
         let mut i2c = Mock::new(&i2c_expectations);
-
         let mut sw3526 = SW3526::new(i2c.clone());
-        sw3526.set_adc_config(AdcConfig::Vin).unwrap();
+        let mut meter = Meter::new();
+
+        assert!(sw3526.update_meter(&mut meter, 0).unwrap().is_none());
+        assert!(sw3526.update_meter(&mut meter, 3_600).unwrap().is_none());
+        assert!(meter.power() == Milliwatts(17856));
+
+        let session = sw3526.update_meter(&mut meter, 4_000).unwrap().unwrap();
+
+        assert!(session.delivered.microwatt_hours() == 17_856);
+        assert!(session.delivered.microamp_hours() == 2_000);
+        assert!(meter.session().is_none());
 
         i2c.done();
     }
 
     #[test]
-    fn get_adc_data_raw() {
+    fn poll_port() {
         let i2c_expectations = [
-            Transaction::write_read(ADDRESS, vec![0x3b], vec![0xff]), // eq 0xff
-            Transaction::write_read(ADDRESS, vec![0x3c], vec![0xff]), // eq 0x0f
+            Transaction::write_read(ADDRESS, vec![0x69], vec![0x40]),
+            Transaction::write_read(ADDRESS, vec![0x07], vec![0x03]),
+            Transaction::write_read(ADDRESS, vec![0x06], vec![0x00]),
+            Transaction::write_read(ADDRESS, vec![0x69], vec![0x40]),
+            Transaction::write_read(ADDRESS, vec![0x07], vec![0x03]),
+            Transaction::write_read(ADDRESS, vec![0x06], vec![0xd6]),
         ];
         let mut i2c = Mock::new(&i2c_expectations);
         let mut sw3526 = SW3526::new(i2c.clone());
+        let mut tracker = PortTracker::new();
 
-        let data = sw3526.get_adc_data_raw().unwrap();
+        let transition = sw3526.poll_port(&mut tracker, 100).unwrap().unwrap();
+        assert!(transition.to == PortState::Attached5V);
 
-        assert!(data == 4095);
+        let transition = sw3526.poll_port(&mut tracker, 300).unwrap().unwrap();
+        assert!(
+            transition.to
+                == PortState::FastCharge {
+                    protocol: ProtocolIndication::PdPps,
+                    pd_version: PdVersion::PD2_0,
+                }
+        );
+        assert!(tracker.attached_at_ms() == Some(100));
 
         i2c.done();
     }
 
     #[test]
-    fn get_adc_data_when_adc_config_not_configured() {
-        let i2c_expectations = [];
+    fn poll_faults() {
+        let i2c_expectations = [
+            Transaction::write_read(ADDRESS, vec![0x0b], vec![0x01]),
+            Transaction::write_read(ADDRESS, vec![0x0b], vec![0x00]),
+        ];
         let mut i2c = Mock::new(&i2c_expectations);
         let mut sw3526 = SW3526::new(i2c.clone());
+        let mut monitor: FaultMonitor = FaultMonitor::new();
 
-        let data = sw3526.get_adc_data().unwrap();
+        sw3526.poll_faults(&mut monitor, 100).unwrap();
+        sw3526.poll_faults(&mut monitor, 200).unwrap();
 
-        assert!(data.is_none());
+        let stats = monitor.stats(AbnormalKind::OutputShortCircuit);
+        assert!(stats.latched);
+        assert!(!stats.active);
+        assert!(stats.last_seen_ms == Some(100));
 
         i2c.done();
     }
 
     #[test]
-    fn get_adc_data_when_adc_config_configured() {
+    fn poll_recovery() {
         let i2c_expectations = [
-            Transaction::write(ADDRESS, vec![0x3a, 0x01]),
-            Transaction::write_read(ADDRESS, vec![0x3b], vec![0xff]), // eq 0xff
-            Transaction::write_read(ADDRESS, vec![0x3c], vec![0xff]), // eq 0x0f
+            Transaction::write_read(ADDRESS, vec![0x0b], vec![0x01]),
+            Transaction::write_read(ADDRESS, vec![0x13], vec![0x00]),
+            Transaction::write(ADDRESS, vec![0x13, 0x80]),
+            Transaction::write_read(ADDRESS, vec![0x0b], vec![0x00]),
+            // read again, the handle is not known to be unlocked
+            Transaction::write_read(ADDRESS, vec![0x13], vec![0x00]),
+            Transaction::write(ADDRESS, vec![0x13, 0x00]),
+            Transaction::write(ADDRESS, vec![0x70, 0x81]),
         ];
         let mut i2c = Mock::new(&i2c_expectations);
-        let mut sw3526 = SW3526::new(i2c.clone());
+        let mut sw3526 = SW3526::new(i2c.clone()).assume_unlocked();
+        let mut recovery = Recovery::new(RecoveryPolicy::default());
 
-        sw3526.set_adc_config(AdcConfig::Vin).unwrap();
-        let data = sw3526.get_adc_data().unwrap();
-
-        assert!(data.is_some());
-        assert!(data.unwrap() == 40950_f32);
+        sw3526.poll_recovery(&mut recovery, 0).unwrap();
+        assert!(matches!(recovery.state(), RecoveryState::Off { .. }));
+        sw3526.poll_recovery(&mut recovery, 1_000).unwrap();
+        assert!(matches!(recovery.state(), RecoveryState::Recovering { .. }));
 
         i2c.done();
     }
 
     #[test]
-    fn get_cc_status() {
-        let i2c_expectations = [Transaction::write_read(ADDRESS, vec![0x69], vec![0xff])]; // eq 0x30
+    fn apply_recovery() {
+        let i2c_expectations = [
+            Transaction::write_read(ADDRESS, vec![0xac], vec![0x01]),
+            Transaction::write(ADDRESS, vec![0xac, 0x05]),
+            Transaction::write_read(ADDRESS, vec![0x13], vec![0x40]),
+            Transaction::write(ADDRESS, vec![0x13, 0xc0]),
+        ];
         let mut i2c = Mock::new(&i2c_expectations);
-        let mut sw3526 = SW3526::new(i2c.clone());
+        let mut sw3526 = SW3526::new(i2c.clone()).assume_unlocked();
 
-        let status = sw3526.get_cc_status().unwrap();
-
-        assert!(status.cc1_attached == true);
-        assert!(status.cc2_attached == true);
+        sw3526
+            .apply_recovery(RecoveryActions {
+                buck_force_off: Some(true),
+                fast_charge_disabled: Some(true),
+                hard_reset: false,
+            })
+            .unwrap();
 
         i2c.done();
     }
@@ -801,7 +2064,7 @@ mod tests {
     fn set_power_command_request() {
         let i2c_expectations = [Transaction::write(ADDRESS, vec![0x70, 0x81])];
         let mut i2c = Mock::new(&i2c_expectations);
-        let mut sw3526 = SW3526::new(i2c.clone());
+        let mut sw3526 = SW3526::new(i2c.clone()).assume_unlocked();
 
         sw3526
             .set_power_command_request(PowerCommandRequest {
@@ -817,7 +2080,7 @@ mod tests {
     fn send_pd_hard_reset() {
         let i2c_expectations = [Transaction::write(ADDRESS, vec![0x70, 0x81])];
         let mut i2c = Mock::new(&i2c_expectations);
-        let mut sw3526 = SW3526::new(i2c.clone());
+        let mut sw3526 = SW3526::new(i2c.clone()).assume_unlocked();
 
         sw3526.send_pd_hard_reset().unwrap();
 
@@ -840,9 +2103,12 @@ mod tests {
 
     #[test]
     fn set_fast_charge_config_6() {
-        let i2c_expectations = [Transaction::write(ADDRESS, vec![0xa2, 0x60])];
+        let i2c_expectations = [
+            Transaction::write_read(ADDRESS, vec![0xa2], vec![0x9f]),
+            Transaction::write(ADDRESS, vec![0xa2, 0xff]),
+        ];
         let mut i2c = Mock::new(&i2c_expectations);
-        let mut sw3526 = SW3526::new(i2c.clone());
+        let mut sw3526 = SW3526::new(i2c.clone()).assume_unlocked();
 
         sw3526
             .set_fast_charge_config_6(FastChargeConfig6 {
@@ -876,9 +2142,12 @@ mod tests {
 
     #[test]
     fn set_fast_charge_config_5() {
-        let i2c_expectations = [Transaction::write(ADDRESS, vec![0xa4, 0x60])];
+        let i2c_expectations = [
+            Transaction::write_read(ADDRESS, vec![0xa4], vec![0x80]),
+            Transaction::write(ADDRESS, vec![0xa4, 0xe0]),
+        ];
         let mut i2c = Mock::new(&i2c_expectations);
-        let mut sw3526 = SW3526::new(i2c.clone());
+        let mut sw3526 = SW3526::new(i2c.clone()).assume_unlocked();
 
         sw3526
             .set_fast_charge_config_5(FastChargeConfig5 {
@@ -898,18 +2167,302 @@ mod tests {
 
         let output = sw3526.get_output_limit_watts().unwrap();
 
-        assert!(output == 63);
+        assert!(output == Watts(63));
 
         i2c.done();
     }
 
     #[test]
     fn set_output_limit_watts() {
-        let i2c_expectations = [Transaction::write(ADDRESS, vec![0xa7, 0x3f])];
+        let i2c_expectations = [
+            Transaction::write_read(ADDRESS, vec![0xa7], vec![0xc0]),
+            Transaction::write(ADDRESS, vec![0xa7, 0xff]),
+        ];
         let mut i2c = Mock::new(&i2c_expectations);
-        let mut sw3526 = SW3526::new(i2c.clone());
+        let mut sw3526 = SW3526::new(i2c.clone()).assume_unlocked();
+
+        sw3526.set_output_limit_watts(Watts(63)).unwrap();
+
+        i2c.done();
+    }
+
+    #[test]
+    fn set_output_limit_watts_wrap_around() {
+        let i2c_expectations = [
+            Transaction::write_read(ADDRESS, vec![0xa7], vec![0x3f]),
+            Transaction::write(ADDRESS, vec![0xa7, 0x07]),
+        ];
+        let mut i2c = Mock::new(&i2c_expectations);
+        let mut sw3526 = SW3526::new(i2c.clone()).assume_unlocked();
+
+        sw3526.set_output_limit_watts(Watts(71)).unwrap();
+
+        i2c.done();
+    }
+
+    #[test]
+    fn poll_derating() {
+        let i2c_expectations = [
+            Transaction::write_read(ADDRESS, vec![0x0b], vec![0x04]),
+            Transaction::write_read(ADDRESS, vec![0xa7], vec![0xc1]),
+            Transaction::write(ADDRESS, vec![0xa7, 0xed]),
+            // the power is set by Rset
+            Transaction::write_read(ADDRESS, vec![0xab], vec![0x00]),
+            Transaction::write(ADDRESS, vec![0xab, 0x01]),
+            Transaction::write_read(ADDRESS, vec![0x0b], vec![0x04]),
+        ];
+        let mut i2c = Mock::new(&i2c_expectations);
+        let mut sw3526 = SW3526::new(i2c.clone()).assume_unlocked();
+        let mut derating = Derating::new(DeratingPolicy::default(), Sw3526::CAPABILITIES).unwrap();
+
+        let event = sw3526.poll_derating(&mut derating, 0).unwrap();
+        assert!(
+            event
+                == Some(DeratingEvent::Derated {
+                    from: Watts(65),
+                    to: Watts(45),
+                })
+        );
+        assert!(sw3526
+            .poll_derating(&mut derating, 1_000)
+            .unwrap()
+            .is_none());
+
+        i2c.done();
+    }
+
+    #[test]
+    fn allocate_power() {
+        fn reading(cc_status: u8, limit: u8) -> [Transaction; 5] {
+            [
+                Transaction::write_read(ADDRESS, vec![0x31], vec![0x00]),
+                Transaction::write_read(ADDRESS, vec![0x33], vec![0x00]),
+                Transaction::write_read(ADDRESS, vec![0x69], vec![cc_status]),
+                Transaction::write_read(ADDRESS, vec![0x06], vec![0x00]),
+                Transaction::write_read(ADDRESS, vec![0x68], vec![limit]),
+            ]
+        }
+
+        // port 0 goes from 65 W to 12 W and port 1 from 12 W set by Rset to 71 W,
+        // it is written although its reg0xA7 already holds 71 W
+        let mut first = Vec::from(reading(0x00, 65));
+        first.push(Transaction::write_read(ADDRESS, vec![0xa7], vec![0x01]));
+        first.push(Transaction::write(ADDRESS, vec![0xa7, 0x0c]));
+        first.push(Transaction::write_read(ADDRESS, vec![0xab], vec![0x01]));
+        let mut second = Vec::from(reading(0x80, 12));
+        second.push(Transaction::write_read(ADDRESS, vec![0xa7], vec![0x07]));
+        second.push(Transaction::write(ADDRESS, vec![0xa7, 0x07]));
+        second.push(Transaction::write_read(ADDRESS, vec![0xab], vec![0x00]));
+        second.push(Transaction::write(ADDRESS, vec![0xab, 0x01]));
+        let mut first_i2c = Mock::new(&first);
+        let mut second_i2c = Mock::new(&second);
+        let mut ports = [
+            SW3526::new(first_i2c.clone()).assume_unlocked(),
+            SW3526::new(second_i2c.clone()).assume_unlocked(),
+        ];
+        let mut allocator = PowerAllocator::new(
+            Watts(83),
+            AllocationPolicy::EqualShare,
+            Sw3526::CAPABILITIES,
+        )
+        .unwrap();
+
+        let allocation = SW3526::allocate_power(&mut ports, &mut allocator).unwrap();
+
+        assert!(allocation.limits == [Watts(12), Watts(71)]);
+        assert!(allocation.readings[0].limit == Watts(65));
+
+        first_i2c.done();
+        second_i2c.done();
+    }
+
+    #[test]
+    fn stage_and_flush() {
+        let i2c_expectations = [
+            Transaction::write_read(ADDRESS, vec![0xa7], vec![0xc1]),
+            Transaction::write_read(ADDRESS, vec![0xac], vec![0x01]),
+            // reg0xAC is staged back to its value and not written
+            Transaction::write(ADDRESS, vec![0xa7, 0xde]),
+            Transaction::write_read(ADDRESS, vec![0xa7], vec![0xde]),
+        ];
+        let mut i2c = Mock::new(&i2c_expectations);
+        let mut sw3526 = SW3526::new(i2c.clone()).assume_unlocked();
+
+        sw3526
+            .stage(|config: &mut PowerConfig| *config = PowerConfig::from_watts(Watts(30)).unwrap())
+            .unwrap();
+        sw3526
+            .stage(|config: &mut FastChargeConfig4| config.port_fast_charge_disabled = true)
+            .unwrap();
+        sw3526
+            .stage(|config: &mut FastChargeConfig4| config.port_fast_charge_disabled = false)
+            .unwrap();
+        assert!(sw3526.has_staged_writes());
+        assert!(sw3526.get_output_limit_watts().unwrap() == Watts(30));
+
+        sw3526.flush().unwrap();
+        assert!(!sw3526.has_staged_writes());
+
+        sw3526.invalidate();
+        assert!(sw3526.get_output_limit_watts().unwrap() == Watts(30));
+
+        i2c.done();
+    }
+
+    /// Sibling of the SW3526 with a mode register at 0xB0 and a 7-bit power limit in reg0xA7
+    struct Sibling;
+
+    impl Chip for Sibling {
+        const NAME: &'static str = "sibling";
+        const ADDRESS: u8 = ADDRESS;
+        const CAPABILITIES: Capabilities = Capabilities {
+            min_power: Watts(12),
+            max_power: Watts(100),
+        };
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum SiblingMode {
+        Normal,
+        Eco,
+    }
+
+    impl ChipRegister<Sibling> for SiblingMode {
+        const ADDRESS: u8 = 0xb0;
+        const MASK: u8 = 0x03;
+
+        fn from_raw(raw: u8) -> Option<Self> {
+            match raw & 0x03 {
+                0 => Some(Self::Normal),
+                1 => Some(Self::Eco),
+                _ => None,
+            }
+        }
+
+        fn into_raw(self) -> u8 {
+            self as u8
+        }
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct SiblingPowerConfig {
+        watts: u8,
+    }
+
+    impl ChipRegister<Sibling> for SiblingPowerConfig {
+        const ADDRESS: u8 = 0xa7;
+        const MASK: u8 = 0x7f;
+
+        fn from_raw(raw: u8) -> Option<Self> {
+            Some(Self { watts: raw & 0x7f })
+        }
+
+        fn into_raw(self) -> u8 {
+            self.watts
+        }
+    }
+
+    #[test]
+    fn chip_registers() {
+        let i2c_expectations = [
+            Transaction::write(ADDRESS, vec![0x12, 0x20]),
+            Transaction::write(ADDRESS, vec![0x12, 0x40]),
+            Transaction::write(ADDRESS, vec![0x12, 0x80]),
+            Transaction::write_read(ADDRESS, vec![0xb0], vec![0xf1]),
+            Transaction::write_read(ADDRESS, vec![0xb0], vec![0xf1]),
+            Transaction::write(ADDRESS, vec![0xb0, 0xf0]),
+            Transaction::write_read(ADDRESS, vec![0xb0], vec![0x03]),
+            // reg0xA7 keeps its reserved bit and its shadow
+            Transaction::write_read(ADDRESS, vec![0xa7], vec![0x80]),
+            Transaction::write(ADDRESS, vec![0xa7, 0xe4]),
+        ];
+        let mut i2c = Mock::new(&i2c_expectations);
+        let mut sibling = Sw35xx::<_, Sibling>::new(i2c.clone()).unlock().unwrap();
+
+        assert!(sibling.read_chip_value::<SiblingMode>().unwrap() == SiblingMode::Eco);
+        sibling.write_chip_value(SiblingMode::Normal).unwrap();
+        assert!(matches!(
+            sibling.read_chip_value::<SiblingMode>(),
+            Err(OperationError::ModelError(
+                ModelError::InvalidChipRegisterValue {
+                    address: 0xb0,
+                    value: 0x03,
+                }
+            ))
+        ));
+
+        sibling
+            .write_chip_value(SiblingPowerConfig { watts: 100 })
+            .unwrap();
+        assert!(
+            sibling
+                .read_chip_value::<SiblingPowerConfig>()
+                .unwrap()
+                .watts
+                == 100
+        );
+
+        i2c.done();
+    }
+
+    #[test]
+    fn poll_foldback() {
+        let i2c_expectations = [
+            Transaction::write_read(ADDRESS, vec![0x30], vec![0x3c]), // 9600 mV
+            Transaction::write_read(ADDRESS, vec![0xa7], vec![0xc1]),
+            Transaction::write(ADDRESS, vec![0xa7, 0xcc]),
+            Transaction::write_read(ADDRESS, vec![0xab], vec![0x01]),
+            Transaction::write_read(ADDRESS, vec![0xac], vec![0x00]),
+            Transaction::write(ADDRESS, vec![0xac, 0x04]),
+            Transaction::write_read(ADDRESS, vec![0x30], vec![0x37]), // 8800 mV
+            Transaction::write_read(ADDRESS, vec![0x13], vec![0x00]),
+            Transaction::write(ADDRESS, vec![0x13, 0x80]),
+        ];
+        let mut i2c = Mock::new(&i2c_expectations);
+        let mut sw3526 = SW3526::new(i2c.clone()).assume_unlocked();
+        let mut foldback = Foldback::new(
+            FoldbackPolicy {
+                nominal: Watts(65),
+                stages: &[FoldbackStage {
+                    below: Millivolts(10000),
+                    limit: Watts(12),
+                    disable_fast_charge: true,
+                }],
+                hysteresis: Millivolts(500),
+                cutoff: Some(Millivolts(9000)),
+            },
+            sw3526.capabilities(),
+        )
+        .unwrap();
+
+        sw3526.poll_foldback(&mut foldback).unwrap();
+        let actions = sw3526.poll_foldback(&mut foldback).unwrap();
+
+        assert!(actions.buck_force_off == Some(true));
+        assert!(foldback.is_cut_off());
+
+        i2c.done();
+    }
 
-        sw3526.set_output_limit_watts(63).unwrap();
+    #[test]
+    fn set_output_limit_watts_out_of_range() {
+        let i2c_expectations = [];
+        let mut i2c = Mock::new(&i2c_expectations);
+        let mut sw3526 = SW3526::new(i2c.clone()).assume_unlocked();
+
+        for watts in [0, 7, 11, 72, 255] {
+            let err = sw3526.set_output_limit_watts(Watts(watts)).unwrap_err();
+
+            assert!(matches!(
+                err,
+                OperationError::ModelError(ModelError::OutOfRange {
+                    register: Register::PowerConfig,
+                    min: 12,
+                    max: 71,
+                    ..
+                })
+            ));
+        }
 
         i2c.done();
     }
@@ -938,7 +2491,7 @@ mod tests {
     fn set_fast_charge_config_0() {
         let i2c_expectations = [Transaction::write(ADDRESS, vec![0xa8, 0x55])];
         let mut i2c = Mock::new(&i2c_expectations);
-        let mut sw3526 = SW3526::new(i2c.clone());
+        let mut sw3526 = SW3526::new(i2c.clone()).assume_unlocked();
 
         sw3526
             .set_fast_charge_config_0(FastChargeConfig0 {
@@ -956,6 +2509,22 @@ mod tests {
         i2c.done();
     }
 
+    #[test]
+    fn modify_fast_charge_config_0() {
+        let i2c_expectations = [
+            Transaction::write_read(ADDRESS, vec![0xa8], vec![0x00]),
+            Transaction::write(ADDRESS, vec![0xa8, 0x40]),
+        ];
+        let mut i2c = Mock::new(&i2c_expectations);
+        let mut sw3526 = SW3526::new(i2c.clone()).assume_unlocked();
+
+        sw3526
+            .modify_fast_charge_config_0(|config| config.vooc_disabled = true)
+            .unwrap();
+
+        i2c.done();
+    }
+
     #[test]
     fn get_fast_charge_config_1() {
         let i2c_expectations = [Transaction::write_read(ADDRESS, vec![0xa9], vec![0xaa])];
@@ -977,9 +2546,12 @@ mod tests {
 
     #[test]
     fn set_fast_charge_config_1() {
-        let i2c_expectations = [Transaction::write(ADDRESS, vec![0xa9, 0x55])];
+        let i2c_expectations = [
+            Transaction::write_read(ADDRESS, vec![0xa9], vec![0x02]), // reserved bit1
+            Transaction::write(ADDRESS, vec![0xa9, 0x57]),
+        ];
         let mut i2c = Mock::new(&i2c_expectations);
-        let mut sw3526 = SW3526::new(i2c.clone());
+        let mut sw3526 = SW3526::new(i2c.clone()).assume_unlocked();
 
         sw3526
             .set_fast_charge_config_1(FastChargeConfig1 {
@@ -996,6 +2568,22 @@ mod tests {
         i2c.done();
     }
 
+    #[test]
+    fn modify_fast_charge_config_1() {
+        let i2c_expectations = [
+            Transaction::write_read(ADDRESS, vec![0xa9], vec![0x82]), // reserved bit1
+            Transaction::write(ADDRESS, vec![0xa9, 0x83]),
+        ];
+        let mut i2c = Mock::new(&i2c_expectations);
+        let mut sw3526 = SW3526::new(i2c.clone()).assume_unlocked();
+
+        sw3526
+            .modify_fast_charge_config_1(|config| config.pd_disabled = true)
+            .unwrap();
+
+        i2c.done();
+    }
+
     #[test]
     fn get_fast_charge_config_2() {
         let i2c_expectations = [Transaction::write_read(ADDRESS, vec![0xaa], vec![0xff])]; // eq 0x23
@@ -1015,9 +2603,12 @@ mod tests {
 
     #[test]
     fn set_fast_charge_config_2() {
-        let i2c_expectations = [Transaction::write(ADDRESS, vec![0xaa, 0x23])];
+        let i2c_expectations = [
+            Transaction::write_read(ADDRESS, vec![0xaa], vec![0xdc]),
+            Transaction::write(ADDRESS, vec![0xaa, 0xff]),
+        ];
         let mut i2c = Mock::new(&i2c_expectations);
-        let mut sw3526 = SW3526::new(i2c.clone());
+        let mut sw3526 = SW3526::new(i2c.clone()).assume_unlocked();
 
         sw3526
             .set_fast_charge_config_2(FastChargeConfig2 {
@@ -1062,9 +2653,12 @@ mod tests {
 
     #[test]
     fn set_fast_charge_config_3() {
-        let i2c_expectations = [Transaction::write(ADDRESS, vec![0xab, 0x54])];
+        let i2c_expectations = [
+            Transaction::write_read(ADDRESS, vec![0xab], vec![0x0e]), // reserved bits 1-3
+            Transaction::write(ADDRESS, vec![0xab, 0x5f]),
+        ];
         let mut i2c = Mock::new(&i2c_expectations);
-        let mut sw3526 = SW3526::new(i2c.clone());
+        let mut sw3526 = SW3526::new(i2c.clone()).assume_unlocked();
 
         sw3526
             .set_fast_charge_config_3(FastChargeConfig3 {
@@ -1095,9 +2689,12 @@ mod tests {
 
     #[test]
     fn set_fast_charge_config_4() {
-        let i2c_expectations = [Transaction::write(ADDRESS, vec![0xac, 0x00])];
+        let i2c_expectations = [
+            Transaction::write_read(ADDRESS, vec![0xac], vec![0xff]),
+            Transaction::write(ADDRESS, vec![0xac, 0xfa]),
+        ];
         let mut i2c = Mock::new(&i2c_expectations);
-        let mut sw3526 = SW3526::new(i2c.clone());
+        let mut sw3526 = SW3526::new(i2c.clone()).assume_unlocked();
 
         sw3526
             .set_fast_charge_config_4(FastChargeConfig4 {
@@ -1111,16 +2708,17 @@ mod tests {
 
     #[test]
     fn get_vid() {
-        let i2c_expectations = [
-            Transaction::write_read(ADDRESS, vec![0xae], vec![0xaa]),
-            Transaction::write_read(ADDRESS, vec![0xaf], vec![0xaa]),
-        ];
+        let i2c_expectations = [Transaction::write_read(
+            ADDRESS,
+            vec![0xae],
+            vec![0x34, 0x12],
+        )];
         let mut i2c = Mock::new(&i2c_expectations);
         let mut sw3526 = SW3526::new(i2c.clone());
 
         let vid = sw3526.get_vid().unwrap();
 
-        assert!(vid == 0xaaaa);
+        assert!(vid == 0x1234);
 
         i2c.done();
     }
@@ -1128,13 +2726,129 @@ mod tests {
     #[test]
     fn set_vid() {
         let i2c_expectations = [
-            Transaction::write(ADDRESS, vec![0xae, 0xaa]),
-            Transaction::write(ADDRESS, vec![0xaf, 0xaa]),
+            Transaction::write(ADDRESS, vec![0xae, 0x34]),
+            Transaction::write(ADDRESS, vec![0xaf, 0x12]),
+        ];
+        let mut i2c = Mock::new(&i2c_expectations);
+        let mut sw3526 = SW3526::new(i2c.clone()).assume_unlocked();
+
+        sw3526.set_vid(0x1234).unwrap();
+
+        i2c.done();
+    }
+
+    #[test]
+    fn get_vid_shadowed() {
+        let i2c_expectations = [
+            Transaction::write(ADDRESS, vec![0x12, 0x20]),
+            Transaction::write(ADDRESS, vec![0x12, 0x40]),
+            Transaction::write(ADDRESS, vec![0x12, 0x80]),
+            Transaction::write(ADDRESS, vec![0xae, 0x34]),
+            Transaction::write(ADDRESS, vec![0xaf, 0x12]),
         ];
         let mut i2c = Mock::new(&i2c_expectations);
+        let mut sw3526 = SW3526::new(i2c.clone()).unlock().unwrap();
+
+        sw3526.set_vid(0x1234).unwrap();
+        sw3526
+            .stage(|config: &mut VidConfig0| config.value = 0x35)
+            .unwrap();
+
+        assert!(sw3526.get_vid().unwrap() == 0x1235);
+
+        i2c.done();
+    }
+
+    fn snapshot_expectations(protocol_again: u8) -> Vec<Transaction> {
+        vec![
+            Transaction::transaction_start(ADDRESS),
+            Transaction::write(ADDRESS, vec![0x03]),
+            Transaction::read(ADDRESS, vec![0x4b, 0x00, 0x3f, 0xa5, 0x03]),
+            Transaction::write(ADDRESS, vec![0x0b]),
+            Transaction::read(ADDRESS, vec![0x04]),
+            Transaction::write(ADDRESS, vec![0x30]),
+            Transaction::read(ADDRESS, vec![0x7d, 0xc8, 0x00, 0x4b]),
+            Transaction::write(ADDRESS, vec![0x68]),
+            Transaction::read(ADDRESS, vec![0x41, 0x40]),
+            Transaction::write(ADDRESS, vec![0x03]),
+            Transaction::read(ADDRESS, vec![0x4b, 0x00, 0x3f, protocol_again, 0x03]),
+            Transaction::write(ADDRESS, vec![0x68]),
+            Transaction::read(ADDRESS, vec![0x41, 0x40]),
+            Transaction::transaction_end(ADDRESS),
+        ]
+    }
+
+    #[test]
+    fn read_snapshot() {
+        let i2c_expectations = snapshot_expectations(0xa5);
+        let mut i2c = Mock::new(&i2c_expectations);
+        let mut sw3526 = SW3526::new(i2c.clone());
+
+        let snapshot = sw3526.read_snapshot().unwrap();
+
+        assert!(matches!(
+            snapshot.protocol.protocol,
+            ProtocolIndication::PdFix
+        ));
+        assert!(matches!(snapshot.protocol.pd_version, PdVersion::PD3_0));
+        assert!(matches!(snapshot.system_status.buck_status, BuckStatus::On));
+        assert!(matches!(
+            snapshot.abnormal_case.over_temperature_alarm_status,
+            OverTemperatureAlarmStatus::Alarm
+        ));
+        assert!(!snapshot.cc_status.cc1_attached);
+        assert!(snapshot.cc_status.cc2_attached);
+        assert!(snapshot.buck_output == Millivolts(12000));
+        assert!(snapshot.buck_output_limit == Milliamps(4150));
+        assert!(snapshot.input_voltage == Millivolts(20000));
+        assert!(snapshot.output_voltage == Millivolts(19200));
+        assert!(snapshot.output_current == Milliamps(3000));
+        assert!(snapshot.limit == Watts(65));
+        assert!(snapshot.coherence == Coherence::Stable);
+
+        i2c.done();
+    }
+
+    #[test]
+    fn next_event() {
+        let mut i2c_expectations = snapshot_expectations(0xa5);
+        i2c_expectations.extend(snapshot_expectations(0xa5));
+        i2c_expectations.extend(snapshot_expectations(0x00));
+        let mut cleared = snapshot_expectations(0xa5);
+        cleared[4] = Transaction::read(ADDRESS, vec![0x00]);
+        i2c_expectations.extend(cleared);
+        let mut i2c = Mock::new(&i2c_expectations);
+        let mut delay = CheckedDelay::new(&vec![DelayTransaction::delay_ms(100); 2]);
         let mut sw3526 = SW3526::new(i2c.clone());
+        let mut detector: EventDetector = EventDetector::new();
+
+        let mut next = || sw3526.next_event(&mut detector, &mut delay, 100).unwrap();
+
+        assert!(next() == Event::Attached { cc: Cc::Cc2 });
+        assert!(
+            next()
+                == Event::ProtocolChanged {
+                    from: ProtocolIndication::Unknown,
+                    to: ProtocolIndication::PdFix,
+                    pd_version: PdVersion::PD3_0,
+                }
+        );
+        assert!(next() == Event::FaultRaised(AbnormalKind::OverTemperatureAlarm));
+        assert!(next() == Event::FaultCleared(AbnormalKind::OverTemperatureAlarm));
+
+        i2c.done();
+        delay.done();
+    }
+
+    #[test]
+    fn read_snapshot_changed() {
+        let i2c_expectations = snapshot_expectations(0x00);
+        let mut i2c = Mock::new(&i2c_expectations);
+        let mut sw3526 = SW3526::new(i2c.clone());
+
+        let snapshot = sw3526.read_snapshot().unwrap();
 
-        sw3526.set_vid(0xaaaa).unwrap();
+        assert!(snapshot.coherence == Coherence::Changed);
 
         i2c.done();
     }
@@ -0,0 +1,19 @@
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Write lock state of reg0xA0~BF, reg0x70~71 and reg0x13, tracked in the type of `SW3526`.
+pub trait LockState: sealed::Sealed {}
+
+/// The protected registers ignore writes, only the getters are available.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Locked;
+
+/// The unlock sequence was sent, the setters are available.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Unlocked;
+
+impl sealed::Sealed for Locked {}
+impl sealed::Sealed for Unlocked {}
+impl LockState for Locked {}
+impl LockState for Unlocked {}
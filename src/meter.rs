@@ -0,0 +1,260 @@
+use crate::{Milliamps, Millivolts, Milliwatts};
+
+/// Samples further apart than this are not integrated, the load is unknown in between.
+pub const DEFAULT_MAX_SAMPLE_INTERVAL_MS: u64 = 10_000;
+
+/// Energy and charge accumulated over time. Both saturate instead of overflowing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Accumulator {
+    /// uW * ms
+    nanojoules: u64,
+    /// mA * ms
+    microcoulombs: u64,
+}
+
+impl Accumulator {
+    pub const ZERO: Self = Self {
+        nanojoules: 0,
+        microcoulombs: 0,
+    };
+
+    fn add(&mut self, microwatts: u64, milliamps: u64, duration_ms: u64) {
+        self.nanojoules = self
+            .nanojoules
+            .saturating_add(microwatts.saturating_mul(duration_ms));
+        self.microcoulombs = self
+            .microcoulombs
+            .saturating_add(milliamps.saturating_mul(duration_ms));
+    }
+
+    pub fn nanojoules(&self) -> u64 {
+        self.nanojoules
+    }
+
+    pub fn microwatt_hours(&self) -> u64 {
+        self.nanojoules / 3_600_000
+    }
+
+    pub fn milliwatt_hours(&self) -> u64 {
+        self.nanojoules / 3_600_000_000
+    }
+
+    pub fn microamp_hours(&self) -> u64 {
+        self.microcoulombs / 3_600
+    }
+
+    pub fn milliamp_hours(&self) -> u64 {
+        self.microcoulombs / 3_600_000
+    }
+}
+
+/// Output measurement at a point in time, `at_ms` comes from the caller's monotonic clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MeterSample {
+    pub at_ms: u64,
+    pub voltage: Millivolts,
+    pub current: Milliamps,
+    /// A sink is attached on CC1 or CC2
+    pub attached: bool,
+}
+
+impl MeterSample {
+    fn microwatts(&self) -> u64 {
+        self.voltage.0 as u64 * self.current.0 as u64
+    }
+}
+
+/// Energy delivered between an attach and the following detach.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Session {
+    pub started_at_ms: u64,
+    /// Time of the last sample of the session
+    pub last_sample_at_ms: u64,
+    pub delivered: Accumulator,
+}
+
+/// Integrates the output power and current of the port.
+///
+/// Energy is integrated with the trapezoidal rule between consecutive samples taken while attached.
+/// A new session starts on attach and the finished one is returned on detach.
+#[derive(Debug, Clone)]
+pub struct Meter {
+    max_interval_ms: u64,
+    last: Option<MeterSample>,
+    total: Accumulator,
+    session: Option<Session>,
+}
+
+impl Default for Meter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Meter {
+    pub const fn new() -> Self {
+        Self::with_max_interval(DEFAULT_MAX_SAMPLE_INTERVAL_MS)
+    }
+
+    pub const fn with_max_interval(max_interval_ms: u64) -> Self {
+        Self {
+            max_interval_ms,
+            last: None,
+            total: Accumulator::ZERO,
+            session: None,
+        }
+    }
+
+    /// Feed a sample. Returns the finished session when the sink was detached.
+    pub fn update(&mut self, sample: MeterSample) -> Option<Session> {
+        let last = self.last.replace(sample);
+
+        if let Some(last) = last.filter(|last| last.attached && sample.attached) {
+            let duration_ms = sample.at_ms.saturating_sub(last.at_ms);
+            if duration_ms <= self.max_interval_ms {
+                let microwatts = last.microwatts() / 2 + sample.microwatts() / 2;
+                let milliamps = (last.current.0 as u64 + sample.current.0 as u64) / 2;
+
+                self.total.add(microwatts, milliamps, duration_ms);
+                if let Some(session) = self.session.as_mut() {
+                    session.delivered.add(microwatts, milliamps, duration_ms);
+                }
+            }
+        }
+
+        if let Some(session) = self.session.as_mut() {
+            session.last_sample_at_ms = sample.at_ms;
+        }
+
+        match (self.session.is_some(), sample.attached) {
+            (false, true) => {
+                self.session = Some(Session {
+                    started_at_ms: sample.at_ms,
+                    last_sample_at_ms: sample.at_ms,
+                    delivered: Accumulator::ZERO,
+                });
+                None
+            }
+            (true, false) => self.session.take(),
+            _ => None,
+        }
+    }
+
+    /// Output power of the last sample.
+    pub fn power(&self) -> Milliwatts {
+        self.last
+            .and_then(|last| last.voltage.checked_power(last.current))
+            .unwrap_or(Milliwatts::ZERO)
+    }
+
+    /// Energy delivered since creation or the last reset.
+    pub fn total(&self) -> Accumulator {
+        self.total
+    }
+
+    /// Session in progress, `None` while detached.
+    pub fn session(&self) -> Option<&Session> {
+        self.session.as_ref()
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::with_max_interval(self.max_interval_ms);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(at_ms: u64, millivolts: u32, milliamps: u32, attached: bool) -> MeterSample {
+        MeterSample {
+            at_ms,
+            voltage: Millivolts(millivolts),
+            current: Milliamps(milliamps),
+            attached,
+        }
+    }
+
+    #[test]
+    fn integrates_energy_and_charge() {
+        let mut meter = Meter::new();
+
+        assert!(meter.update(sample(0, 9000, 2000, true)).is_none());
+        assert!(meter.update(sample(1_000, 9000, 2000, true)).is_none());
+        assert!(meter.update(sample(2_000, 9000, 2000, true)).is_none());
+
+        assert_eq!(meter.power(), Milliwatts(18000));
+        // 18 W for 2 s
+        assert_eq!(meter.total().nanojoules(), 36_000_000_000);
+        assert_eq!(meter.total().microwatt_hours(), 10_000);
+        assert_eq!(meter.total().microamp_hours(), 1_111);
+        assert_eq!(meter.session().unwrap().delivered, meter.total());
+    }
+
+    #[test]
+    fn trapezoidal() {
+        let mut meter = Meter::new();
+
+        meter.update(sample(0, 5000, 0, true));
+        meter.update(sample(3_600, 5000, 2000, true));
+
+        // average 5 W for 3.6 s
+        assert_eq!(meter.total().microwatt_hours(), 5_000);
+        assert_eq!(meter.total().microamp_hours(), 1_000);
+    }
+
+    #[test]
+    fn session_split_on_detach() {
+        let mut meter = Meter::new();
+
+        meter.update(sample(0, 5000, 1000, true));
+        meter.update(sample(3_600, 5000, 1000, true));
+        let session = meter.update(sample(4_000, 5000, 0, false)).unwrap();
+
+        assert_eq!(session.started_at_ms, 0);
+        assert_eq!(session.last_sample_at_ms, 4_000);
+        assert_eq!(session.delivered.microwatt_hours(), 5_000);
+        assert!(meter.session().is_none());
+
+        meter.update(sample(5_000, 9000, 1000, true));
+        meter.update(sample(8_600, 9000, 1000, true));
+
+        assert_eq!(meter.session().unwrap().started_at_ms, 5_000);
+        assert_eq!(meter.session().unwrap().delivered.microwatt_hours(), 9_000);
+        assert_eq!(meter.total().microwatt_hours(), 14_000);
+    }
+
+    #[test]
+    fn skips_long_gaps() {
+        let mut meter = Meter::with_max_interval(1_000);
+
+        meter.update(sample(0, 5000, 1000, true));
+        meter.update(sample(5_000, 5000, 1000, true));
+
+        assert_eq!(meter.total(), Accumulator::ZERO);
+    }
+
+    #[test]
+    fn saturates() {
+        let mut meter = Meter::with_max_interval(u64::MAX);
+
+        meter.update(sample(0, u32::MAX, u32::MAX, true));
+        meter.update(sample(u64::MAX, u32::MAX, u32::MAX, true));
+        meter.update(sample(u64::MAX, u32::MAX, u32::MAX, true));
+
+        assert_eq!(meter.total().nanojoules(), u64::MAX);
+    }
+
+    #[test]
+    fn reset() {
+        let mut meter = Meter::new();
+
+        meter.update(sample(0, 5000, 1000, true));
+        meter.update(sample(1_000, 5000, 1000, true));
+        meter.reset();
+
+        assert_eq!(meter.total(), Accumulator::ZERO);
+        assert!(meter.session().is_none());
+        assert_eq!(meter.power(), Milliwatts::ZERO);
+    }
+}
@@ -1,54 +1,22 @@
 use num_enum::TryFromPrimitive;
 
-type ModelError = crate::error::ModelError;
+use crate::{Microamps, Milliamps, Millivolts, ModelError, Watts};
 
-#[repr(u8)]
-pub enum Register {
-    VersionInfo = 0x01,
-    BuckOutputVoltageHigh8b = 0x03,
-    BuckOutputVoltageLow4b = 0x04,
-    BuckOutputCurrentLimit = 0x05,
-    ProtocolIndication = 0x06,
-    SystemStatus = 0x07,
-    AbnormalCase = 0x0B,
-    I2cEnable = 0x12,
-    BuckForceOff = 0x13,
-    AdcVinData = 0x30,
-    AdcVoutData = 0x31,
-    AdcIoutData = 0x33,
-    AdcConfig = 0x3A,
-    AdcDataHigh8b = 0x3B,
-    AdcDataLow4b = 0x3C,
-    PowerStatus = 0x68,
-    CcStatus = 0x69,
-    PowerCommandRequest = 0x70,
-    FastChargeConfig6 = 0xA2,
-    FastChargeConfig5 = 0xA4,
-    PowerConfig = 0xA7,
-    FastChargeConfig0 = 0xA8,
-    FastChargeConfig1 = 0xA9,
-    FastChargeConfig2 = 0xAA,
-    FastChargeConfig3 = 0xAB,
-    FastChargeConfig4 = 0xAC,
-    VidConfig0 = 0xAE,
-    VidConfig1 = 0xAF,
-}
-
-#[derive(TryFromPrimitive, Debug, Clone, Copy)]
+#[derive(TryFromPrimitive, Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum ProtocolStatus {
     OffLine = 0,
     OnLine = 1,
 }
 
-#[derive(TryFromPrimitive, Debug, Clone, Copy)]
+#[derive(TryFromPrimitive, Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum VoltageStatus {
     _5V = 0,
     ProtocolVoltage = 1,
 }
 
-#[derive(TryFromPrimitive, Debug, Clone, Copy)]
+#[derive(TryFromPrimitive, Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum PdVersion {
     Unknown = 0,
@@ -56,7 +24,7 @@ pub enum PdVersion {
     PD3_0 = 2,
 }
 
-#[derive(TryFromPrimitive, Debug, Clone, Copy)]
+#[derive(TryFromPrimitive, Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum ProtocolIndication {
     Unknown = 0,
@@ -73,474 +41,504 @@ pub enum ProtocolIndication {
     AFC = 11,
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct ProtocolIndicationResponse {
-    pub protocol_status: ProtocolStatus,
-    pub voltage_status: VoltageStatus,
-    pub pd_version: PdVersion,
-    pub protocol: ProtocolIndication,
-}
-
-impl From<u8> for ProtocolIndicationResponse {
-    fn from(value: u8) -> Self {
-        Self {
-            protocol_status: ((value & 0x80) >> 7).try_into().unwrap(),
-            voltage_status: ((value & 0x40) >> 6).try_into().unwrap(),
-            pd_version: ((value & 0x30) >> 4)
-                .try_into()
-                .unwrap_or(PdVersion::Unknown),
-            protocol: (value & 0x0F)
-                .try_into()
-                .unwrap_or(ProtocolIndication::Unknown),
-        }
-    }
-}
-
-impl From<ProtocolIndicationResponse> for u8 {
-    fn from(value: ProtocolIndicationResponse) -> Self {
-        (value.protocol_status as u8) << 7
-            | (value.voltage_status as u8) << 6
-            | (value.pd_version as u8) << 4
-            | (value.protocol as u8)
-    }
-}
-
-#[derive(TryFromPrimitive, Debug, Clone, Copy)]
+#[derive(TryFromPrimitive, Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum PortStatus {
     Off = 0,
     On = 1,
 }
 
-#[derive(TryFromPrimitive, Debug, Clone, Copy)]
+#[derive(TryFromPrimitive, Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum BuckStatus {
     Off = 0,
     On = 1,
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct SystemStatusResponse {
-    pub port_status: PortStatus,
-    pub buck_status: BuckStatus,
-}
-
-impl From<u8> for SystemStatusResponse {
-    fn from(value: u8) -> Self {
-        Self {
-            port_status: ((value & 0x02) >> 1).try_into().unwrap(),
-            buck_status: (value & 0x01).try_into().unwrap(),
-        }
-    }
-}
-
-impl From<SystemStatusResponse> for u8 {
-    fn from(value: SystemStatusResponse) -> Self {
-        (value.port_status as u8) << 1 | (value.buck_status as u8)
-    }
-}
-
-#[derive(TryFromPrimitive, Debug, Clone, Copy)]
+#[derive(TryFromPrimitive, Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum VinOvpStatus {
     Normal = 0,
     Ovp = 1,
 }
 
-#[derive(TryFromPrimitive, Debug, Clone, Copy)]
+#[derive(TryFromPrimitive, Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum OverTemperatureAlarmStatus {
     Normal = 0,
     Alarm = 1,
 }
 
-#[derive(TryFromPrimitive, Debug, Clone, Copy)]
+#[derive(TryFromPrimitive, Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum OverTemperatureShutdownStatus {
     Normal = 0,
     Shutdown = 1,
 }
 
-#[derive(TryFromPrimitive, Debug, Clone, Copy)]
+#[derive(TryFromPrimitive, Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum OutputShortCircuitStatus {
     Normal = 0,
     Short = 1,
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct AbnormalCaseResponse {
-    pub vin_ovp_status: VinOvpStatus,
-    pub over_temperature_alarm_status: OverTemperatureAlarmStatus,
-    pub over_temperature_shutdown_status: OverTemperatureShutdownStatus,
-    pub output_short_circuit_status: OutputShortCircuitStatus,
-}
-
-impl From<u8> for AbnormalCaseResponse {
-    fn from(value: u8) -> Self {
-        Self {
-            vin_ovp_status: ((value & 0x10) >> 4).try_into().unwrap(),
-            over_temperature_alarm_status: ((value & 0x04) >> 2).try_into().unwrap(),
-            over_temperature_shutdown_status: ((value & 0x02) >> 1).try_into().unwrap(),
-            output_short_circuit_status: (value & 0x01).try_into().unwrap(),
-        }
-    }
-}
-
-impl From<AbnormalCaseResponse> for u8 {
-    fn from(value: AbnormalCaseResponse) -> Self {
-        (value.vin_ovp_status as u8) << 4
-            | (value.over_temperature_alarm_status as u8) << 2
-            | (value.over_temperature_shutdown_status as u8) << 1
-            | (value.output_short_circuit_status as u8)
-    }
-}
-
-#[derive(TryFromPrimitive, Debug, Clone, Copy)]
+#[derive(TryFromPrimitive, Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum BuckForceOff {
     Nothing = 0,
     TurnOffOneSecond = 1,
 }
 
-#[derive(TryFromPrimitive, Debug, Clone, Copy)]
+#[derive(TryFromPrimitive, Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum CCUnDrivenDurationBuckForceOff {
     Driven = 0,
     UnDriven = 1,
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct BuckForceOffConfig {
-    pub force_off: BuckForceOff,
-    pub cc_un_driven_duration_buck_force_off: CCUnDrivenDurationBuckForceOff,
+#[derive(TryFromPrimitive, Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum AdcConfig {
+    Vin = 1,
+    Vout = 2,
+    Iout = 3,
 }
 
-impl From<u8> for BuckForceOffConfig {
-    fn from(value: u8) -> Self {
-        Self {
-            force_off: BuckForceOff::try_from((value & 0x80) >> 7).unwrap(),
-            cc_un_driven_duration_buck_force_off: CCUnDrivenDurationBuckForceOff::try_from(
-                (value & 0x40) >> 6,
-            )
-            .unwrap(),
-        }
-    }
+#[derive(TryFromPrimitive, Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PdCommand {
+    /// No command, the reset value.
+    None = 0,
+    HardReset = 1,
 }
 
-impl From<BuckForceOffConfig> for u8 {
-    fn from(value: BuckForceOffConfig) -> Self {
-        (value.force_off as u8) << 7 | (value.cc_un_driven_duration_buck_force_off as u8) << 6
-    }
+#[derive(TryFromPrimitive, Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ScpSelect {
+    LowVoltage = 0,
+    HighVoltage = 1,
 }
 
-#[derive(TryFromPrimitive, Debug, Clone, Copy)]
+#[derive(TryFromPrimitive, Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
-pub enum AdcConfig {
-    Vin = 1,
-    Vout = 2,
-    Iout = 3,
+pub enum Pe2_0MaxVoltage {
+    _12V = 0,
+    _20V = 1,
 }
 
-#[derive(Debug)]
-pub struct CcStatus {
-    pub cc1_attached: bool,
-    pub cc2_attached: bool,
+#[derive(TryFromPrimitive, Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum MaxOutputVoltageExceptPd {
+    SameWithPd = 0,
+    _9V = 1,
+    _12V = 2,
+    _20V = 3,
 }
 
-impl From<u8> for CcStatus {
-    fn from(value: u8) -> Self {
-        Self {
-            cc1_attached: (value & 0x80) != 0,
-            cc2_attached: (value & 0x40) != 0,
-        }
-    }
+#[derive(TryFromPrimitive, Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PdCurrentLimitProtectMethod {
+    /// UV mode, output voltage will go down when output current larger than current limit threshold
+    UV = 0,
+    /// OC mode, ouput voltage will reset to 5v when output current larger than current limit threshold
+    OC = 1,
 }
 
-impl From<CcStatus> for u8 {
-    fn from(value: CcStatus) -> Self {
-        (value.cc1_attached as u8) << 7 | (value.cc2_attached as u8) << 6
-    }
+#[derive(TryFromPrimitive, Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum QC3_0CurrentLimitProtectMethod {
+    CCLoop = 0,
+    VoltageDrop = 1,
 }
 
-#[derive(TryFromPrimitive, Debug, Clone, Copy)]
+#[derive(TryFromPrimitive, Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
-pub enum PdCommand {
-    HardReset = 1,
+pub enum PowerSettingMethod {
+    /// Rset resistance
+    Rset = 0,
+    /// Register reg0xA7
+    Register = 1,
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct PowerCommandRequest {
-    /// PD source command send enable
-    /// Write true, the command in (reg0x70[3:0]) will send. This bit is automatically cleared by hardware.
-    pub send_enabled: bool,
-    pub command: PdCommand,
-}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cc {
+    Cc1,
+    Cc2,
+}
+
+/// Conditions reported by reg0x0B.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AbnormalKind {
+    VinOvp,
+    OverTemperatureAlarm,
+    OverTemperatureShutdown,
+    OutputShortCircuit,
+}
+
+impl AbnormalKind {
+    pub const ALL: [AbnormalKind; 4] = [
+        AbnormalKind::VinOvp,
+        AbnormalKind::OverTemperatureAlarm,
+        AbnormalKind::OverTemperatureShutdown,
+        AbnormalKind::OutputShortCircuit,
+    ];
+}
+
+enum_fields!(
+    ProtocolStatus,
+    VoltageStatus,
+    PortStatus,
+    BuckStatus,
+    VinOvpStatus,
+    OverTemperatureAlarmStatus,
+    OverTemperatureShutdownStatus,
+    OutputShortCircuitStatus,
+    BuckForceOff,
+    CCUnDrivenDurationBuckForceOff,
+    PdCommand,
+    ScpSelect,
+    Pe2_0MaxVoltage,
+    MaxOutputVoltageExceptPd,
+    PdCurrentLimitProtectMethod,
+    QC3_0CurrentLimitProtectMethod,
+    PowerSettingMethod,
+);
+
+/// Unused encodings are reported as `Unknown`.
+impl crate::register::Field for PdVersion {
+    fn from_bits(bits: u8) -> Option<Self> {
+        Some(Self::try_from(bits).unwrap_or(Self::Unknown))
+    }
 
-impl TryFrom<u8> for PowerCommandRequest {
-    type Error = ModelError;
-    fn try_from(value: u8) -> Result<Self, Self::Error> {
-        Ok(Self {
-            send_enabled: (value & 0x80) != 0,
-            command: PdCommand::try_from(value & 0x07)
-                .map_err(|_| ModelError::InvalidValueU8(value))?,
-        })
+    fn into_bits(self) -> u8 {
+        self as u8
     }
 }
 
-impl From<PowerCommandRequest> for u8 {
-    fn from(value: PowerCommandRequest) -> Self {
-        (value.send_enabled as u8) << 3 | (value.command as u8)
+/// Unused encodings are reported as `Unknown`.
+impl crate::register::Field for ProtocolIndication {
+    fn from_bits(bits: u8) -> Option<Self> {
+        Some(Self::try_from(bits).unwrap_or(Self::Unknown))
     }
-}
 
-impl Default for PowerCommandRequest {
-    fn default() -> Self {
-        Self {
-            send_enabled: false,
-            command: PdCommand::HardReset,
-        }
+    fn into_bits(self) -> u8 {
+        self as u8
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct FastChargeConfig6 {
-    pub qc2_0_qc3_0_cable_compatible_and_offset_enabled: bool,
-    pub pdo_link_with_vin: bool,
-}
+registers! {
+    VersionInfo = 0x01, ReadOnly, reset = _ => VersionInfo {
+        version: u8 [1:0],
+    }
 
-impl From<u8> for FastChargeConfig6 {
-    fn from(value: u8) -> Self {
-        Self {
-            qc2_0_qc3_0_cable_compatible_and_offset_enabled: (value & 0x40) != 0,
-            pdo_link_with_vin: (value & 0x20) != 0,
-        }
+    BuckOutputVoltageHigh8b = 0x03, ReadOnly, reset = _ => BuckOutputVoltageHigh8b {
+        high: u8 [7:0],
     }
-}
 
-impl From<FastChargeConfig6> for u8 {
-    fn from(value: FastChargeConfig6) -> Self {
-        (value.qc2_0_qc3_0_cable_compatible_and_offset_enabled as u8) << 6
-            | (value.pdo_link_with_vin as u8) << 5
+    BuckOutputVoltageLow4b = 0x04, ReadOnly, reset = _ => BuckOutputVoltageLow4b {
+        low: u8 [7:4],
     }
-}
 
-#[derive(TryFromPrimitive, Debug, Clone, Copy)]
-#[repr(u8)]
-pub enum ScpSelect {
-    LowVoltage = 0,
-    HighVoltage = 1,
-}
+    BuckOutputCurrentLimit = 0x05, ReadOnly, reset = _ => BuckOutputCurrentLimit {
+        limit: u8 [5:0],
+    }
 
-#[derive(TryFromPrimitive, Debug, Clone, Copy)]
-#[repr(u8)]
-pub enum Pe2_0MaxVoltage {
-    _12V = 0,
-    _20V = 1,
-}
+    ProtocolIndication = 0x06, ReadOnly, reset = _ => ProtocolIndicationResponse {
+        protocol_status: ProtocolStatus [7],
+        voltage_status: VoltageStatus [6],
+        pd_version: PdVersion [5:4],
+        protocol: ProtocolIndication [3:0],
+    }
 
-#[derive(Debug, Clone, Copy)]
-pub struct FastChargeConfig5 {
-    pub scp_select: ScpSelect,
-    pub pe2_0_max_voltage: Pe2_0MaxVoltage,
-}
+    SystemStatus = 0x07, ReadOnly, reset = _ => SystemStatusResponse {
+        port_status: PortStatus [1],
+        buck_status: BuckStatus [0],
+    }
 
-impl From<u8> for FastChargeConfig5 {
-    fn from(value: u8) -> Self {
-        Self {
-            scp_select: ScpSelect::try_from((value & 0x40) >> 6).unwrap(),
-            pe2_0_max_voltage: Pe2_0MaxVoltage::try_from((value & 0x20) >> 5).unwrap(),
-        }
+    AbnormalCase = 0x0B, ReadOnly, reset = _ => AbnormalCaseResponse {
+        vin_ovp_status: VinOvpStatus [4],
+        over_temperature_alarm_status: OverTemperatureAlarmStatus [2],
+        over_temperature_shutdown_status: OverTemperatureShutdownStatus [1],
+        output_short_circuit_status: OutputShortCircuitStatus [0],
     }
-}
 
-impl From<FastChargeConfig5> for u8 {
-    fn from(value: FastChargeConfig5) -> Self {
-        (value.scp_select as u8) << 6 | (value.pe2_0_max_voltage as u8) << 5
+    /// Write 0x20, 0x40 then 0x80 to make reg0xA0~BF, reg0x70~71 and reg0x13 writable.
+    I2cEnable = 0x12, ReadWrite, reset = 0x00 => I2cEnable {
+        value: u8 [7:0],
     }
-}
 
-#[derive(Debug, Clone, Copy)]
-pub struct FastChargeConfig0 {
-    pub scp_disabled: bool,
-    pub vooc_disabled: bool,
-    pub sfcp_disabled: bool,
-    pub qc2_0_disabled: bool,
-    pub qc3_0_disabled: bool,
-    pub fcp_disabled: bool,
-    pub afc_disabled: bool,
-    pub pe_disabled: bool,
-}
+    BuckForceOff = 0x13, ReadWrite, reset = 0x00 => BuckForceOffConfig {
+        force_off: BuckForceOff [7],
+        cc_un_driven_duration_buck_force_off: CCUnDrivenDurationBuckForceOff [6],
+    }
 
-impl From<u8> for FastChargeConfig0 {
-    fn from(value: u8) -> Self {
-        Self {
-            scp_disabled: (value & 0x80) != 0,
-            vooc_disabled: (value & 0x40) != 0,
-            sfcp_disabled: (value & 0x20) != 0,
-            qc2_0_disabled: (value & 0x10) != 0,
-            qc3_0_disabled: (value & 0x08) != 0,
-            fcp_disabled: (value & 0x04) != 0,
-            afc_disabled: (value & 0x02) != 0,
-            pe_disabled: (value & 0x01) != 0,
-        }
+    AdcVinData = 0x30, ReadOnly, reset = _ => AdcVinData {
+        vin: u8 [7:0],
     }
-}
 
-impl From<FastChargeConfig0> for u8 {
-    fn from(value: FastChargeConfig0) -> Self {
-        (value.scp_disabled as u8) << 7
-            | (value.vooc_disabled as u8) << 6
-            | (value.sfcp_disabled as u8) << 5
-            | (value.qc2_0_disabled as u8) << 4
-            | (value.qc3_0_disabled as u8) << 3
-            | (value.fcp_disabled as u8) << 2
-            | (value.afc_disabled as u8) << 1
-            | (value.pe_disabled as u8)
+    AdcVoutData = 0x31, ReadOnly, reset = _ => AdcVoutData {
+        vout: u8 [7:0],
+    }
+
+    AdcIoutData = 0x33, ReadOnly, reset = _ => AdcIoutData {
+        iout: u8 [7:0],
+    }
+
+    /// Selects what reg0x3B~3C report.
+    AdcConfig = 0x3A, ReadWrite, reset = 0x00 => AdcDataSelect {
+        /// `None` until a channel is selected, reg0x3B~3C then read 0.
+        config: Option<AdcConfig> [1:0],
+    }
+
+    AdcDataHigh8b = 0x3B, ReadOnly, reset = _ => AdcDataHigh8b {
+        high: u8 [7:0],
+    }
+
+    AdcDataLow4b = 0x3C, ReadOnly, reset = _ => AdcDataLow4b {
+        low: u8 [3:0],
+    }
+
+    /// Power value set by Rset or reg0xA7, in watts.
+    PowerStatus = 0x68, ReadOnly, reset = _ => PowerStatus {
+        limit_watts: u8 [6:0],
     }
-}
 
-#[derive(Debug, Clone, Copy)]
-pub struct FastChargeConfig1 {
-    pub pps1_disabled: bool,
-    pub pps0_disabled: bool,
-    pub pd_20v_disabled: bool,
-    pub pd_15v_disabled: bool,
-    pub pd_12v_disabled: bool,
-    pub pd_9v_disabled: bool,
-    pub pd_disabled: bool,
+    CcStatus = 0x69, ReadOnly, reset = _ => CcStatus {
+        cc1_attached: bool [7],
+        cc2_attached: bool [6],
+    }
+
+    PowerCommandRequest = 0x70, ReadWrite, reset = 0x00 => PowerCommandRequest {
+        /// PD source command send enable
+        /// Write true, the command in `command` (reg0x70 bits 2~0) will send. This bit is automatically cleared by hardware.
+        send_enabled: bool [7],
+        command: PdCommand [2:0],
+    }
+
+    FastChargeConfig6 = 0xA2, ReadWrite, reset = _ => FastChargeConfig6 {
+        qc2_0_qc3_0_cable_compatible_and_offset_enabled: bool [6],
+        pdo_link_with_vin: bool [5],
+    }
+
+    FastChargeConfig5 = 0xA4, ReadWrite, reset = _ => FastChargeConfig5 {
+        scp_select: ScpSelect [6],
+        pe2_0_max_voltage: Pe2_0MaxVoltage [5],
+    }
+
+    /// Output power limit in watts, 0~7 stand for 64~71 W.
+    PowerConfig = 0xA7, ReadWrite, reset = _ => PowerConfig {
+        watts: u8 [5:0],
+    }
+
+    FastChargeConfig0 = 0xA8, ReadWrite, reset = _ => FastChargeConfig0 {
+        scp_disabled: bool [7],
+        vooc_disabled: bool [6],
+        sfcp_disabled: bool [5],
+        qc2_0_disabled: bool [4],
+        qc3_0_disabled: bool [3],
+        fcp_disabled: bool [2],
+        afc_disabled: bool [1],
+        pe_disabled: bool [0],
+    }
+
+    FastChargeConfig1 = 0xA9, ReadWrite, reset = _ => FastChargeConfig1 {
+        pps1_disabled: bool [7],
+        pps0_disabled: bool [6],
+        pd_20v_disabled: bool [5],
+        pd_15v_disabled: bool [4],
+        pd_12v_disabled: bool [3],
+        pd_9v_disabled: bool [2],
+        pd_disabled: bool [0],
+    }
+
+    FastChargeConfig2 = 0xAA, ReadWrite, reset = _ => FastChargeConfig2 {
+        dpdm_enabled: bool [5],
+        max_output_voltage_except_pd: MaxOutputVoltageExceptPd [1:0],
+    }
+
+    FastChargeConfig3 = 0xAB, ReadWrite, reset = _ => FastChargeConfig3 {
+        pd_current_limit_protect_method: PdCurrentLimitProtectMethod [7],
+        qc3_0_current_limit_protect_method: QC3_0CurrentLimitProtectMethod [6],
+        qc3_0_constant_power_enabled: bool [5],
+        pps_constant_power_enabled: bool [4],
+        power_setting_method: PowerSettingMethod [0],
+    }
+
+    FastChargeConfig4 = 0xAC, ReadWrite, reset = _ => FastChargeConfig4 {
+        /// if true, close all fast charge protocols
+        port_fast_charge_disabled: bool [2],
+        /// if true, source capability will be send again with 5V/2A PDO after device request 5V/3A PDO
+        pd_5v_2a_rebroadcast_enabled: bool [0],
+    }
+
+    VidConfig0 = 0xAE, ReadWrite, reset = _ => VidConfig0 {
+        value: u8 [7:0],
+    }
+
+    VidConfig1 = 0xAF, ReadWrite, reset = _ => VidConfig1 {
+        value: u8 [7:0],
+    }
 }
 
-impl From<u8> for FastChargeConfig1 {
-    fn from(value: u8) -> Self {
+impl Default for PowerCommandRequest {
+    fn default() -> Self {
         Self {
-            pps1_disabled: (value & 0x80) != 0,
-            pps0_disabled: (value & 0x40) != 0,
-            pd_20v_disabled: (value & 0x20) != 0,
-            pd_15v_disabled: (value & 0x10) != 0,
-            pd_12v_disabled: (value & 0x08) != 0,
-            pd_9v_disabled: (value & 0x04) != 0,
-            pd_disabled: (value & 0x01) != 0,
+            send_enabled: false,
+            command: PdCommand::HardReset,
         }
     }
 }
 
-impl From<FastChargeConfig1> for u8 {
-    fn from(value: FastChargeConfig1) -> Self {
-        (value.pps1_disabled as u8) << 7
-            | (value.pps0_disabled as u8) << 6
-            | (value.pd_20v_disabled as u8) << 5
-            | (value.pd_15v_disabled as u8) << 4
-            | (value.pd_12v_disabled as u8) << 3
-            | (value.pd_9v_disabled as u8) << 2
-            | (value.pd_disabled as u8)
+impl PowerConfig {
+    pub const MIN: Watts = Watts(12);
+    pub const MAX: Watts = Watts(71);
+
+    /// Encode a power limit, 64~71 W wrap around to 0~7.
+    pub fn from_watts(watts: Watts) -> Result<Self, ModelError> {
+        ModelError::check_range(
+            Register::PowerConfig,
+            watts.0 as u32,
+            Self::MIN.0 as u32,
+            Self::MAX.0 as u32,
+        )?;
+
+        Ok(Self {
+            watts: watts.0 & 0x3f,
+        })
     }
-}
 
-#[derive(TryFromPrimitive, Debug, Clone, Copy)]
-#[repr(u8)]
-pub enum MaxOutputVoltageExceptPd {
-    SameWithPd = 0,
-    _9V = 1,
-    _12V = 2,
-    _20V = 3,
-}
-#[derive(Debug, Clone, Copy)]
-pub struct FastChargeConfig2 {
-    pub dpdm_enabled: bool,
-    pub max_output_voltage_except_pd: MaxOutputVoltageExceptPd,
+    pub fn to_watts(self) -> Watts {
+        if self.watts < 8 {
+            Watts(self.watts + 64)
+        } else {
+            Watts(self.watts)
+        }
+    }
 }
 
-impl From<u8> for FastChargeConfig2 {
-    fn from(value: u8) -> Self {
-        Self {
-            dpdm_enabled: (value & 0x20) != 0,
-            max_output_voltage_except_pd: (value & 0x03).try_into().unwrap(),
-        }
+impl BuckOutputVoltageHigh8b {
+    /// Buck output voltage set point, 10 mV per LSB of the 12-bit value.
+    pub fn millivolts(self, low: BuckOutputVoltageLow4b) -> Millivolts {
+        Millivolts((((self.high as u32) << 4) | (low.low as u32)) * 10)
     }
 }
 
-impl From<FastChargeConfig2> for u8 {
-    fn from(value: FastChargeConfig2) -> Self {
-        (value.dpdm_enabled as u8) << 5 | (value.max_output_voltage_except_pd as u8)
+impl BuckOutputCurrentLimit {
+    pub fn milliamps(self) -> Milliamps {
+        Milliamps(1000 + (self.limit as u32) * 50)
     }
 }
 
-#[derive(TryFromPrimitive, Debug, Clone, Copy)]
-#[repr(u8)]
-pub enum PdCurrentLimitProtectMethod {
-    /// UV mode, output voltage will go down when output current larger than current limit threshold
-    UV = 0,
-    /// OC mode, ouput voltage will reset to 5v when output current larger than current limit threshold
-    OC = 1,
+impl AdcVinData {
+    pub fn millivolts(self) -> Millivolts {
+        Millivolts(((self.vin as u32) << 4) * 10)
+    }
 }
 
-#[derive(TryFromPrimitive, Debug, Clone, Copy)]
-#[repr(u8)]
-pub enum QC3_0CurrentLimitProtectMethod {
-    CCLoop = 0,
-    VoltageDrop = 1,
+impl AdcVoutData {
+    pub fn millivolts(self) -> Millivolts {
+        Millivolts(((self.vout as u32) << 4) * 6)
+    }
 }
 
-#[derive(TryFromPrimitive, Debug, Clone, Copy)]
-#[repr(u8)]
-pub enum PowerSettingMethod {
-    /// Rset resistance
-    Rset = 0,
-    /// Register reg0xA7
-    Register = 1,
+impl AdcIoutData {
+    pub fn milliamps(self) -> Milliamps {
+        // 2.5 mA per LSB of the 12-bit value, the low 4 bits are not available here.
+        Milliamps((self.iout as u32) * 40)
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct FastChargeConfig3 {
-    pub pd_current_limit_protect_method: PdCurrentLimitProtectMethod,
-    pub qc3_0_current_limit_protect_method: QC3_0CurrentLimitProtectMethod,
-    pub qc3_0_constant_power_enabled: bool,
-    pub pps_constant_power_enabled: bool,
-    pub power_setting_method: PowerSettingMethod,
+impl PowerStatus {
+    pub fn watts(self) -> Watts {
+        Watts(self.limit_watts)
+    }
 }
 
-impl From<u8> for FastChargeConfig3 {
-    fn from(value: u8) -> Self {
-        Self {
-            pd_current_limit_protect_method: ((value & 0x80) >> 7).try_into().unwrap(),
-            qc3_0_current_limit_protect_method: ((value & 0x40) >> 6).try_into().unwrap(),
-            qc3_0_constant_power_enabled: (value & 0x20) != 0,
-            pps_constant_power_enabled: (value & 0x10) != 0,
-            power_setting_method: (value & 0x01).try_into().unwrap(),
+impl CcStatus {
+    /// A sink is attached on either CC line.
+    pub fn is_attached(self) -> bool {
+        self.cc1_attached || self.cc2_attached
+    }
+
+    /// CC line the sink is attached on, CC1 if both report it.
+    pub fn cc(self) -> Option<Cc> {
+        if self.cc1_attached {
+            Some(Cc::Cc1)
+        } else if self.cc2_attached {
+            Some(Cc::Cc2)
+        } else {
+            None
         }
     }
 }
 
-impl From<FastChargeConfig3> for u8 {
-    fn from(value: FastChargeConfig3) -> Self {
-        (value.pd_current_limit_protect_method as u8) << 7
-            | (value.qc3_0_current_limit_protect_method as u8) << 6
-            | (value.qc3_0_constant_power_enabled as u8) << 5
-            | (value.pps_constant_power_enabled as u8) << 4
-            | (value.power_setting_method as u8) << 2
+impl AbnormalCaseResponse {
+    /// The condition `kind` is reported.
+    pub fn is_active(self, kind: AbnormalKind) -> bool {
+        match kind {
+            AbnormalKind::VinOvp => self.vin_ovp_status == VinOvpStatus::Ovp,
+            AbnormalKind::OverTemperatureAlarm => {
+                self.over_temperature_alarm_status == OverTemperatureAlarmStatus::Alarm
+            }
+            AbnormalKind::OverTemperatureShutdown => {
+                self.over_temperature_shutdown_status == OverTemperatureShutdownStatus::Shutdown
+            }
+            AbnormalKind::OutputShortCircuit => {
+                self.output_short_circuit_status == OutputShortCircuitStatus::Short
+            }
+        }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct FastChargeConfig4 {
-    /// if true, close all fast charge protocols
-    pub port_fast_charge_disabled: bool,
-    /// if true, source capability will be send again with 5V/2A PDO after device request 5V/3A PDO
-    pub pd_5v_2a_rebroadcast_enabled: bool,
+/// Encoding 0 of reg0x3A selects no channel.
+impl crate::register::Field for Option<AdcConfig> {
+    fn from_bits(bits: u8) -> Option<Self> {
+        match bits {
+            0 => Some(None),
+            bits => AdcConfig::try_from(bits).ok().map(Some),
+        }
+    }
+
+    fn into_bits(self) -> u8 {
+        self.map_or(0, |config| config as u8)
+    }
 }
 
-impl From<u8> for FastChargeConfig4 {
-    fn from(value: u8) -> Self {
-        Self {
-            port_fast_charge_disabled: (value & 0x04) != 0,
-            pd_5v_2a_rebroadcast_enabled: (value & 0x01) != 0,
+impl AdcConfig {
+    /// Channel following this one in round-robin measurements.
+    pub fn next(self) -> Self {
+        match self {
+            AdcConfig::Vin => AdcConfig::Vout,
+            AdcConfig::Vout => AdcConfig::Iout,
+            AdcConfig::Iout => AdcConfig::Vin,
         }
     }
-}
 
-impl From<FastChargeConfig4> for u8 {
-    fn from(value: FastChargeConfig4) -> Self {
-        (value.port_fast_charge_disabled as u8) << 2 | (value.pd_5v_2a_rebroadcast_enabled as u8)
+    /// Convert the 12-bit ADC data of reg0x3B~3C.
+    pub fn data(self, raw: u16) -> AdcData {
+        let raw = raw as u32;
+        match self {
+            AdcConfig::Vin => AdcData::Vin(Millivolts(raw * 10)),
+            AdcConfig::Vout => AdcData::Vout(Millivolts(raw * 6)),
+            // 2.5 mA per LSB
+            AdcConfig::Iout => AdcData::Iout(Microamps(raw * 2500)),
+        }
     }
 }
+
+/// ADC data in the unit selected by [`AdcConfig`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdcData {
+    Vin(Millivolts),
+    Vout(Millivolts),
+    Iout(Microamps),
+}
+
+/// Full resolution measurements of every ADC channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AdcMeasurements {
+    pub vin: Millivolts,
+    pub vout: Millivolts,
+    pub iout: Microamps,
+}
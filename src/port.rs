@@ -0,0 +1,215 @@
+use crate::{
+    CcStatus, PdVersion, PortStatus, ProtocolIndication, ProtocolIndicationResponse,
+    ProtocolStatus, Sw3526Snapshot, SystemStatusResponse,
+};
+
+/// Lifecycle state of the port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PortState {
+    /// No sink on CC1 or CC2
+    Detached,
+    /// Sink attached, no fast charge protocol detected
+    Attached5V,
+    /// A protocol is detected but it is not online yet
+    Negotiating { protocol: ProtocolIndication },
+    /// A fast charge protocol is online
+    FastCharge {
+        protocol: ProtocolIndication,
+        pd_version: PdVersion,
+    },
+    /// Sink attached but the chip turned the port off
+    Fault,
+}
+
+impl PortState {
+    /// Derive the state from one poll of the status registers.
+    pub fn from_status(
+        cc_status: CcStatus,
+        system_status: SystemStatusResponse,
+        protocol: ProtocolIndicationResponse,
+    ) -> Self {
+        if !cc_status.is_attached() {
+            return Self::Detached;
+        }
+        if system_status.port_status == PortStatus::Off {
+            return Self::Fault;
+        }
+
+        match (protocol.protocol_status, protocol.protocol) {
+            (ProtocolStatus::OnLine, _) => Self::FastCharge {
+                protocol: protocol.protocol,
+                pd_version: protocol.pd_version,
+            },
+            (ProtocolStatus::OffLine, ProtocolIndication::Unknown) => Self::Attached5V,
+            (ProtocolStatus::OffLine, protocol) => Self::Negotiating { protocol },
+        }
+    }
+
+    pub fn is_attached(self) -> bool {
+        self != Self::Detached
+    }
+}
+
+impl From<&Sw3526Snapshot> for PortState {
+    fn from(value: &Sw3526Snapshot) -> Self {
+        Self::from_status(value.cc_status, value.system_status, value.protocol)
+    }
+}
+
+/// Change of [`PortState`], `at_ms` is the time of the poll that observed it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortTransition {
+    pub from: PortState,
+    pub to: PortState,
+    pub at_ms: u64,
+}
+
+/// Follows the port lifecycle across periodic polls of the status registers.
+///
+/// Times come from a monotonic clock of the caller, in milliseconds.
+#[derive(Debug, Clone)]
+pub struct PortTracker {
+    state: PortState,
+    entered_at_ms: u64,
+    attached_at_ms: Option<u64>,
+    last_transition: Option<PortTransition>,
+}
+
+impl Default for PortTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PortTracker {
+    /// Start detached at time 0.
+    pub const fn new() -> Self {
+        Self {
+            state: PortState::Detached,
+            entered_at_ms: 0,
+            attached_at_ms: None,
+            last_transition: None,
+        }
+    }
+
+    /// Feed the state of a poll. Returns the transition if the state changed.
+    pub fn update(&mut self, state: PortState, now_ms: u64) -> Option<PortTransition> {
+        if state == self.state {
+            return None;
+        }
+
+        let transition = PortTransition {
+            from: self.state,
+            to: state,
+            at_ms: now_ms,
+        };
+
+        match (self.state.is_attached(), state.is_attached()) {
+            (false, true) => self.attached_at_ms = Some(now_ms),
+            (true, false) => self.attached_at_ms = None,
+            _ => {}
+        }
+        self.state = state;
+        self.entered_at_ms = now_ms;
+        self.last_transition = Some(transition);
+
+        Some(transition)
+    }
+
+    /// Feed a snapshot, see [`PortTracker::update`].
+    pub fn update_snapshot(
+        &mut self,
+        snapshot: &Sw3526Snapshot,
+        now_ms: u64,
+    ) -> Option<PortTransition> {
+        self.update(snapshot.into(), now_ms)
+    }
+
+    pub fn state(&self) -> PortState {
+        self.state
+    }
+
+    /// Time the current state was entered.
+    pub fn entered_at_ms(&self) -> u64 {
+        self.entered_at_ms
+    }
+
+    /// Time spent in the current state.
+    pub fn time_in_state_ms(&self, now_ms: u64) -> u64 {
+        now_ms.saturating_sub(self.entered_at_ms)
+    }
+
+    /// Time the sink was attached, `None` while detached.
+    pub fn attached_at_ms(&self) -> Option<u64> {
+        self.attached_at_ms
+    }
+
+    pub fn last_transition(&self) -> Option<PortTransition> {
+        self.last_transition
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(cc: u8, system: u8, protocol: u8) -> PortState {
+        PortState::from_status(
+            cc.try_into().unwrap(),
+            system.try_into().unwrap(),
+            protocol.try_into().unwrap(),
+        )
+    }
+
+    #[test]
+    fn from_status() {
+        assert_eq!(state(0x00, 0x03, 0x00), PortState::Detached);
+        assert_eq!(state(0x80, 0x03, 0x00), PortState::Attached5V);
+        assert_eq!(state(0x40, 0x01, 0x00), PortState::Fault);
+        assert_eq!(
+            state(0x80, 0x03, 0x02),
+            PortState::Negotiating {
+                protocol: ProtocolIndication::QC3_0
+            }
+        );
+        assert_eq!(
+            state(0x80, 0x03, 0xe5),
+            PortState::FastCharge {
+                protocol: ProtocolIndication::PdFix,
+                pd_version: PdVersion::PD3_0
+            }
+        );
+    }
+
+    #[test]
+    fn transitions() {
+        let mut tracker = PortTracker::new();
+        let fast_charge = PortState::FastCharge {
+            protocol: ProtocolIndication::PdFix,
+            pd_version: PdVersion::PD3_0,
+        };
+
+        assert_eq!(tracker.update(PortState::Detached, 100), None);
+        assert_eq!(
+            tracker.update(PortState::Attached5V, 200),
+            Some(PortTransition {
+                from: PortState::Detached,
+                to: PortState::Attached5V,
+                at_ms: 200
+            })
+        );
+        assert_eq!(tracker.attached_at_ms(), Some(200));
+
+        assert!(tracker.update(fast_charge, 500).is_some());
+        assert_eq!(tracker.update(fast_charge, 900), None);
+        assert_eq!(tracker.state(), fast_charge);
+        assert_eq!(tracker.entered_at_ms(), 500);
+        assert_eq!(tracker.time_in_state_ms(900), 400);
+        assert_eq!(tracker.attached_at_ms(), Some(200));
+
+        let transition = tracker.update(PortState::Detached, 1000).unwrap();
+        assert_eq!(transition.from, fast_charge);
+        assert_eq!(tracker.attached_at_ms(), None);
+        assert_eq!(tracker.last_transition(), Some(transition));
+    }
+}
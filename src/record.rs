@@ -0,0 +1,554 @@
+use embedded_hal::i2c::{self, ErrorKind, ErrorType, I2c, NoAcknowledgeSource, Operation};
+
+// Every record is `header, address, [timestamp: u64 LE], length, bytes`.
+const OP_MASK: u8 = 0x03;
+const OP_WRITE: u8 = 0;
+const OP_READ: u8 = 1;
+const OP_ERROR: u8 = 2;
+/// The record belongs to the same transaction as the previous one
+const CONTINUES: u8 = 0x40;
+const TIMESTAMP: u8 = 0x80;
+
+/// One operation of a recorded transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordedOp<'a> {
+    /// Bytes written, the first one is the register address
+    Write(&'a [u8]),
+    /// Bytes returned by the chip
+    Read(&'a [u8]),
+    /// The whole transaction failed, its operations are not recorded
+    Error(ErrorKind),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Record<'a> {
+    pub address: u8,
+    /// First operation of a transaction
+    pub starts_transaction: bool,
+    /// Time of the transaction, on the first operation, when the recorder has a clock
+    pub timestamp: Option<u64>,
+    /// Register the operation starts at, the chip keeps the address of the last write for reads
+    pub register: u8,
+    pub op: RecordedOp<'a>,
+}
+
+/// The recording ends in the middle of a record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeError {
+    /// Offset of the record in the recording
+    pub offset: usize,
+}
+
+/// Transactions encoded by a [`Recorder`], see [`Recording::records`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Recording<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Recording<'a> {
+    /// Wrap bytes produced by [`Recorder::recording`], for example read back from a file.
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    pub fn records(&self) -> Records<'a> {
+        Records {
+            bytes: self.bytes,
+            offset: 0,
+            register: 0,
+        }
+    }
+}
+
+/// Iterator over the records of a [`Recording`].
+#[derive(Debug, Clone)]
+pub struct Records<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    register: u8,
+}
+
+impl Records<'_> {
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Result<Record<'a>, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let start = self.offset;
+        let rest = self.bytes.get(start..).filter(|rest| !rest.is_empty())?;
+        let error = DecodeError { offset: start };
+
+        let (&header, rest) = rest.split_first()?;
+        let Some((&address, mut rest)) = rest.split_first() else {
+            return Some(Err(error));
+        };
+        let mut timestamp = None;
+        if header & TIMESTAMP != 0 {
+            let Some(time) = rest
+                .get(..8)
+                .and_then(|time| <[u8; 8]>::try_from(time).ok())
+            else {
+                return Some(Err(error));
+            };
+            timestamp = Some(u64::from_le_bytes(time));
+            rest = &rest[8..];
+        }
+        let Some((&len, rest)) = rest.split_first() else {
+            return Some(Err(error));
+        };
+        let Some(bytes) = rest.get(..len as usize) else {
+            return Some(Err(error));
+        };
+
+        let op = match (header & OP_MASK, bytes) {
+            (OP_WRITE, _) => RecordedOp::Write(bytes),
+            (OP_READ, _) => RecordedOp::Read(bytes),
+            (OP_ERROR, &[code]) => RecordedOp::Error(decode_error(code)),
+            _ => return Some(Err(error)),
+        };
+        self.offset = self.bytes.len() - rest.len() + len as usize;
+
+        let register = self.register;
+        match op {
+            RecordedOp::Write(&[first, ref values @ ..]) => {
+                self.register = first.wrapping_add(values.len() as u8);
+            }
+            RecordedOp::Read(values) => self.register = register.wrapping_add(values.len() as u8),
+            _ => {}
+        }
+
+        Some(Ok(Record {
+            address,
+            starts_transaction: header & CONTINUES == 0,
+            timestamp,
+            register: match op {
+                RecordedOp::Write(&[first, ..]) => first,
+                _ => register,
+            },
+            op,
+        }))
+    }
+}
+
+fn encode_error(kind: ErrorKind) -> u8 {
+    match kind {
+        ErrorKind::Bus => 0,
+        ErrorKind::ArbitrationLoss => 1,
+        ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address) => 2,
+        ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data) => 3,
+        ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown) => 4,
+        ErrorKind::Overrun => 5,
+        _ => 6,
+    }
+}
+
+fn decode_error(code: u8) -> ErrorKind {
+    match code {
+        0 => ErrorKind::Bus,
+        1 => ErrorKind::ArbitrationLoss,
+        2 => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address),
+        3 => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data),
+        4 => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown),
+        5 => ErrorKind::Overrun,
+        _ => ErrorKind::Other,
+    }
+}
+
+/// Transparent `I2c` wrapper recording every transaction into a byte buffer.
+///
+/// Transactions are recorded once they complete, with the bytes read.
+/// A failed transaction is recorded as its error only.
+/// Recording stops when the buffer is full, see [`Recorder::is_truncated`].
+#[derive(Debug)]
+pub struct Recorder<'b, I2C, F = fn() -> u64> {
+    i2c: I2C,
+    buffer: &'b mut [u8],
+    len: usize,
+    truncated: bool,
+    clock: Option<F>,
+}
+
+impl<'b, I2C> Recorder<'b, I2C> {
+    pub fn new(i2c: I2C, buffer: &'b mut [u8]) -> Self {
+        Self {
+            i2c,
+            buffer,
+            len: 0,
+            truncated: false,
+            clock: None,
+        }
+    }
+}
+
+impl<'b, I2C, F> Recorder<'b, I2C, F> {
+    /// Timestamp every transaction with `clock`, in the unit of the caller.
+    pub fn with_clock<G: FnMut() -> u64>(self, clock: G) -> Recorder<'b, I2C, G> {
+        Recorder {
+            i2c: self.i2c,
+            buffer: self.buffer,
+            len: self.len,
+            truncated: self.truncated,
+            clock: Some(clock),
+        }
+    }
+
+    pub fn recording(&self) -> Recording<'_> {
+        Recording::new(&self.buffer[..self.len])
+    }
+
+    /// A transaction did not fit in the buffer, it and the following ones are missing.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Start a new recording in the same buffer.
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.truncated = false;
+    }
+
+    pub fn into_inner(self) -> I2C {
+        self.i2c
+    }
+
+    fn record<E: i2c::Error>(
+        &mut self,
+        address: u8,
+        timestamp: Option<u64>,
+        operations: &[Operation<'_>],
+        result: &Result<(), E>,
+    ) {
+        if self.truncated {
+            return;
+        }
+
+        // header, address and length, the timestamp is only on the first record
+        let timestamp_len = if timestamp.is_some() { 8 } else { 0 };
+        let size = match result {
+            Ok(()) => operations
+                .iter()
+                .map(|operation| match operation {
+                    Operation::Write(bytes) => bytes.len(),
+                    Operation::Read(buffer) => buffer.len(),
+                })
+                .try_fold(timestamp_len, |size, len| {
+                    (len <= u8::MAX as usize).then_some(size + 3 + len)
+                }),
+            Err(_) => Some(timestamp_len + 4),
+        };
+        if !matches!(size, Some(size) if self.len + size <= self.buffer.len()) {
+            self.truncated = true;
+            return;
+        }
+
+        let mut header = if timestamp.is_some() { TIMESTAMP } else { 0 };
+        match result {
+            Ok(()) => {
+                for operation in operations {
+                    let (op, bytes): (u8, &[u8]) = match operation {
+                        Operation::Write(bytes) => (OP_WRITE, bytes),
+                        Operation::Read(buffer) => (OP_READ, buffer),
+                    };
+                    self.push(header | op, address, timestamp, bytes);
+                    // only the first record of a transaction is timestamped
+                    header = CONTINUES;
+                }
+            }
+            Err(error) => self.push(
+                header | OP_ERROR,
+                address,
+                timestamp,
+                &[encode_error(error.kind())],
+            ),
+        }
+    }
+
+    fn push(&mut self, header: u8, address: u8, timestamp: Option<u64>, bytes: &[u8]) {
+        let mut put = |data: &[u8]| {
+            self.buffer[self.len..self.len + data.len()].copy_from_slice(data);
+            self.len += data.len();
+        };
+        put(&[header, address]);
+        if header & TIMESTAMP != 0 {
+            put(&timestamp.unwrap_or(0).to_le_bytes());
+        }
+        put(&[bytes.len() as u8]);
+        put(bytes);
+    }
+}
+
+impl<I2C: ErrorType, F> ErrorType for Recorder<'_, I2C, F> {
+    type Error = I2C::Error;
+}
+
+impl<I2C: I2c, F: FnMut() -> u64> I2c for Recorder<'_, I2C, F> {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let timestamp = self.clock.as_mut().map(|clock| clock());
+        let result = self.i2c.transaction(address, operations);
+        self.record(address, timestamp, operations, &result);
+        result
+    }
+}
+
+#[cfg(feature = "async")]
+impl<I2C, F> embedded_hal_async::i2c::I2c for Recorder<'_, I2C, F>
+where
+    I2C: embedded_hal_async::i2c::I2c,
+    F: FnMut() -> u64,
+{
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        let timestamp = self.clock.as_mut().map(|clock| clock());
+        let result = self.i2c.transaction(address, operations).await;
+        self.record(address, timestamp, operations, &result);
+        result
+    }
+}
+
+/// Error returned by a [`Replay`] bus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The error recorded for this transaction
+    Recorded(ErrorKind),
+    /// The driver issued a transaction different from the recording, at `offset` of the recording
+    Mismatch {
+        offset: usize,
+    },
+    /// The recording is exhausted
+    End,
+    Decode(DecodeError),
+}
+
+impl i2c::Error for ReplayError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            ReplayError::Recorded(kind) => *kind,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+/// `I2c` bus playing back a [`Recording`].
+///
+/// Every transaction must match the recorded one: same address, operations and written bytes.
+/// Reads return the recorded bytes and recorded errors are returned again.
+#[derive(Debug, Clone)]
+pub struct Replay<'a> {
+    records: Records<'a>,
+}
+
+impl<'a> Replay<'a> {
+    pub fn new(recording: Recording<'a>) -> Self {
+        Self {
+            records: recording.records(),
+        }
+    }
+
+    /// The whole recording was played.
+    pub fn is_finished(&self) -> bool {
+        self.records.clone().next().is_none()
+    }
+
+    fn play(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<(), ReplayError> {
+        let offset = self.records.offset();
+        let mismatch = ReplayError::Mismatch { offset };
+        let record = self
+            .records
+            .next()
+            .ok_or(ReplayError::End)?
+            .map_err(ReplayError::Decode)?;
+        if record.address != address || !record.starts_transaction {
+            return Err(mismatch);
+        }
+        if let RecordedOp::Error(kind) = record.op {
+            return Err(ReplayError::Recorded(kind));
+        }
+
+        let mut next = Some(record);
+        for operation in operations.iter_mut() {
+            let record = match next.take() {
+                Some(record) => record,
+                None => self
+                    .records
+                    .next()
+                    .ok_or(ReplayError::End)?
+                    .map_err(ReplayError::Decode)?,
+            };
+            if record.address != address {
+                return Err(mismatch);
+            }
+            match (operation, record.op) {
+                (Operation::Write(bytes), RecordedOp::Write(recorded)) if *bytes == recorded => {}
+                (Operation::Read(buffer), RecordedOp::Read(recorded))
+                    if buffer.len() == recorded.len() =>
+                {
+                    buffer.copy_from_slice(recorded);
+                }
+                _ => return Err(mismatch),
+            }
+        }
+
+        match self.records.clone().next() {
+            Some(Ok(record)) if !record.starts_transaction => Err(mismatch),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl ErrorType for Replay<'_> {
+    type Error = ReplayError;
+}
+
+impl I2c for Replay<'_> {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.play(address, operations)
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::i2c::I2c for Replay<'_> {
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.play(address, operations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Cc, Chip, Milliamps, Millivolts, Sw3526, Sw3526Sim, Watts, SW3526};
+
+    const ADDRESS: u8 = Sw3526::ADDRESS;
+
+    #[test]
+    fn encoding() {
+        let sim = Sw3526Sim::new();
+        let mut buffer = [0u8; 64];
+        let mut ticks = 1233;
+        let mut recorder = Recorder::new(sim.bus(), &mut buffer).with_clock(move || {
+            ticks += 1;
+            ticks
+        });
+
+        let mut version = [0u8; 1];
+        recorder.write_read(ADDRESS, &[0x01], &mut version).unwrap();
+        assert_eq!(
+            recorder.recording().as_bytes(),
+            &[
+                0x80, ADDRESS, 0xd2, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 1, 0x01, 0x41,
+                ADDRESS, 1, 0x00
+            ]
+        );
+
+        sim.set_responding(false);
+        assert!(recorder.write(ADDRESS, &[0x12, 0x20]).is_err());
+
+        let recording = recorder.recording();
+        let mut records = recording.records();
+        let write = records.next().unwrap().unwrap();
+        assert_eq!(write.timestamp, Some(1234));
+        assert!(write.starts_transaction);
+        assert_eq!(write.op, RecordedOp::Write(&[0x01]));
+        let read = records.next().unwrap().unwrap();
+        assert_eq!(read.register, 0x01);
+        assert!(!read.starts_transaction);
+        assert_eq!(read.op, RecordedOp::Read(&[0x00]));
+        assert_eq!(
+            records.next().unwrap().unwrap().op,
+            RecordedOp::Error(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address))
+        );
+        assert!(records.next().is_none());
+
+        assert_eq!(
+            Recording::new(&[0x00, ADDRESS, 2, 0x01]).records().next(),
+            Some(Err(DecodeError { offset: 0 }))
+        );
+    }
+
+    #[test]
+    fn truncated() {
+        let sim = Sw3526Sim::new();
+        let mut buffer = [0u8; 8];
+        let mut recorder = Recorder::new(sim.bus(), &mut buffer);
+
+        let mut data = [0u8; 2];
+        recorder.write_read(ADDRESS, &[0x03], &mut data).unwrap();
+        assert!(recorder.is_truncated());
+        assert!(recorder.recording().as_bytes().is_empty());
+    }
+
+    #[test]
+    fn replay() {
+        let sim = Sw3526Sim::new();
+        sim.attach(Cc::Cc1);
+        sim.set_vout(Millivolts(9000));
+        sim.set_iout(Milliamps(1500));
+        let mut buffer = [0u8; 256];
+
+        let mut recorder = Recorder::new(sim.bus(), &mut buffer);
+        let mut sw3526 = SW3526::new(&mut recorder);
+        let snapshot = sw3526.read_snapshot().unwrap();
+        let mut sw3526 = sw3526.unlock().unwrap();
+        sw3526.set_output_limit_watts(Watts(30)).unwrap();
+        sim.set_responding(false);
+        assert!(sw3526.get_cc_status().is_err());
+        assert!(!recorder.is_truncated());
+
+        let mut replay = Replay::new(recorder.recording());
+        let mut sw3526 = SW3526::new(&mut replay);
+        assert_eq!(sw3526.read_snapshot().unwrap(), snapshot);
+        let mut sw3526 = sw3526.unlock().unwrap();
+        sw3526.set_output_limit_watts(Watts(30)).unwrap();
+        assert!(matches!(
+            sw3526.get_cc_status(),
+            Err(crate::OperationError::I2CError(ReplayError::Recorded(
+                ErrorKind::NoAcknowledge(_)
+            )))
+        ));
+        assert!(replay.is_finished());
+    }
+
+    #[test]
+    fn replay_mismatch() {
+        let sim = Sw3526Sim::new();
+        let mut buffer = [0u8; 64];
+        let mut recorder = Recorder::new(sim.bus(), &mut buffer);
+        SW3526::new(&mut recorder).get_chip_version().unwrap();
+
+        let mut replay = Replay::new(recorder.recording());
+        assert!(matches!(
+            SW3526::new(&mut replay).get_cc_status(),
+            Err(crate::OperationError::I2CError(ReplayError::Mismatch {
+                offset: 0
+            }))
+        ));
+
+        let mut replay = Replay::new(Recording::new(&[]));
+        assert!(matches!(
+            SW3526::new(&mut replay).get_cc_status(),
+            Err(crate::OperationError::I2CError(ReplayError::End))
+        ));
+    }
+}
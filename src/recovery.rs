@@ -0,0 +1,347 @@
+use crate::{AbnormalCaseResponse, AbnormalKind, Sw3526Snapshot};
+
+/// How [`Recovery`] reacts to faults.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecoveryPolicy {
+    /// Faults that turn the port off
+    pub triggers: &'static [AbnormalKind],
+    /// Attempts to turn the port back on before escalating
+    pub retries: u8,
+    /// Lock the port to 5 V once the retries are exhausted, instead of latching off right away
+    pub lock_to_5v: bool,
+    /// Attempts to turn the port back on while locked to 5 V before latching off
+    pub retries_at_5v: u8,
+    /// Time the port stays off after the first trip
+    pub backoff_ms: u32,
+    /// The off time is multiplied by this factor on every following trip
+    pub backoff_factor: u32,
+    pub max_backoff_ms: u32,
+    /// Send a PD hard reset after turning the port back on
+    pub hard_reset: bool,
+    /// Time without fault after which the attempts are forgotten
+    pub stable_ms: u32,
+}
+
+impl Default for RecoveryPolicy {
+    fn default() -> Self {
+        Self {
+            triggers: &[
+                AbnormalKind::OutputShortCircuit,
+                AbnormalKind::OverTemperatureShutdown,
+            ],
+            retries: 3,
+            lock_to_5v: true,
+            retries_at_5v: 2,
+            backoff_ms: 1_000,
+            backoff_factor: 2,
+            max_backoff_ms: 30_000,
+            hard_reset: true,
+            stable_ms: 10_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveryState {
+    Normal,
+    /// Port turned off after a trip, until `until_ms` and the fault is gone
+    Off {
+        attempt: u8,
+        until_ms: u64,
+    },
+    /// Port turned back on since `since_ms`, waiting to be stable
+    Recovering {
+        attempt: u8,
+        since_ms: u64,
+    },
+    /// Retries exhausted, the port stays off until [`Recovery::reset`]
+    LatchedOff,
+}
+
+/// Register writes requested by [`Recovery`], applied by `SW3526::apply_recovery`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RecoveryActions {
+    /// `Some(true)` to force the buck off, `Some(false)` to release it (reg0x13)
+    pub buck_force_off: Option<bool>,
+    /// `Some(true)` to lock the port to 5 V, `Some(false)` to allow fast charge again (reg0xAC)
+    pub fast_charge_disabled: Option<bool>,
+    /// Send a PD hard reset (reg0x70)
+    pub hard_reset: bool,
+}
+
+impl RecoveryActions {
+    pub const NONE: Self = Self {
+        buck_force_off: None,
+        fast_charge_disabled: None,
+        hard_reset: false,
+    };
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::NONE
+    }
+}
+
+/// Fault recovery driven by polls of reg0x0B.
+///
+/// A trip forces the buck off for an exponential backoff, then turns the port back on.
+/// Once the retries are exhausted the port is locked to 5 V, then latched off.
+/// The chip only forces the buck off for one second, so the write is repeated on every poll
+/// while the port is off; poll more often than once a second.
+/// Writing reg0x13 and reg0xAC needs `SW3526::set_i2c_writable` first.
+#[derive(Debug, Clone)]
+pub struct Recovery {
+    policy: RecoveryPolicy,
+    state: RecoveryState,
+    locked_to_5v: bool,
+    attempts: u8,
+}
+
+impl Recovery {
+    pub fn new(policy: RecoveryPolicy) -> Self {
+        Self {
+            policy,
+            state: RecoveryState::Normal,
+            locked_to_5v: false,
+            attempts: 0,
+        }
+    }
+
+    /// Feed a poll of reg0x0B. Returns the writes to apply.
+    pub fn update(&mut self, abnormal_case: AbnormalCaseResponse, now_ms: u64) -> RecoveryActions {
+        let faulted = self
+            .policy
+            .triggers
+            .iter()
+            .any(|kind| abnormal_case.is_active(*kind));
+
+        match self.state {
+            RecoveryState::Normal if faulted => self.trip(now_ms),
+            RecoveryState::Normal => RecoveryActions::NONE,
+            RecoveryState::Off { attempt, until_ms } if now_ms >= until_ms && !faulted => {
+                self.state = RecoveryState::Recovering {
+                    attempt,
+                    since_ms: now_ms,
+                };
+                RecoveryActions {
+                    buck_force_off: Some(false),
+                    hard_reset: self.policy.hard_reset,
+                    ..RecoveryActions::NONE
+                }
+            }
+            RecoveryState::Off { .. } | RecoveryState::LatchedOff => RecoveryActions {
+                buck_force_off: Some(true),
+                ..RecoveryActions::NONE
+            },
+            RecoveryState::Recovering { .. } if faulted => self.trip(now_ms),
+            RecoveryState::Recovering { since_ms, .. } => {
+                if now_ms.saturating_sub(since_ms) >= self.policy.stable_ms as u64 {
+                    self.state = RecoveryState::Normal;
+                    self.attempts = 0;
+                }
+                RecoveryActions::NONE
+            }
+        }
+    }
+
+    /// Feed a snapshot, see [`Recovery::update`].
+    pub fn update_snapshot(&mut self, snapshot: &Sw3526Snapshot, now_ms: u64) -> RecoveryActions {
+        self.update(snapshot.abnormal_case, now_ms)
+    }
+
+    fn trip(&mut self, now_ms: u64) -> RecoveryActions {
+        let mut actions = RecoveryActions {
+            buck_force_off: Some(true),
+            ..RecoveryActions::NONE
+        };
+
+        self.attempts = self.attempts.saturating_add(1);
+        let retries = if self.locked_to_5v {
+            self.policy.retries_at_5v
+        } else {
+            self.policy.retries
+        };
+
+        if self.attempts > retries {
+            if self.locked_to_5v || !self.policy.lock_to_5v {
+                self.state = RecoveryState::LatchedOff;
+                return actions;
+            }
+
+            self.locked_to_5v = true;
+            self.attempts = 1;
+            actions.fast_charge_disabled = Some(true);
+        }
+
+        let backoff_ms = (1..self.attempts)
+            .fold(self.policy.backoff_ms, |backoff, _| {
+                backoff.saturating_mul(self.policy.backoff_factor)
+            })
+            .min(self.policy.max_backoff_ms);
+        self.state = RecoveryState::Off {
+            attempt: self.attempts,
+            until_ms: now_ms.saturating_add(backoff_ms as u64),
+        };
+
+        actions
+    }
+
+    pub fn state(&self) -> RecoveryState {
+        self.state
+    }
+
+    pub fn is_locked_to_5v(&self) -> bool {
+        self.locked_to_5v
+    }
+
+    pub fn policy(&self) -> &RecoveryPolicy {
+        &self.policy
+    }
+
+    /// Return to normal operation. Returns the writes undoing the lock to 5 V and the force off.
+    pub fn reset(&mut self) -> RecoveryActions {
+        let actions = RecoveryActions {
+            buck_force_off: matches!(
+                self.state,
+                RecoveryState::Off { .. } | RecoveryState::LatchedOff
+            )
+            .then_some(false),
+            fast_charge_disabled: self.locked_to_5v.then_some(false),
+            hard_reset: false,
+        };
+
+        self.state = RecoveryState::Normal;
+        self.locked_to_5v = false;
+        self.attempts = 0;
+        actions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHORT: u8 = 0x01;
+    const NORMAL: u8 = 0x00;
+
+    fn poll(recovery: &mut Recovery, abnormal: u8, now_ms: u64) -> RecoveryActions {
+        recovery.update(abnormal.try_into().unwrap(), now_ms)
+    }
+
+    const OFF: RecoveryActions = RecoveryActions {
+        buck_force_off: Some(true),
+        fast_charge_disabled: None,
+        hard_reset: false,
+    };
+
+    const ON: RecoveryActions = RecoveryActions {
+        buck_force_off: Some(false),
+        fast_charge_disabled: None,
+        hard_reset: true,
+    };
+
+    #[test]
+    fn retry_with_backoff() {
+        let mut recovery = Recovery::new(RecoveryPolicy::default());
+
+        assert!(poll(&mut recovery, NORMAL, 0).is_empty());
+        assert_eq!(poll(&mut recovery, SHORT, 100), OFF);
+        assert_eq!(
+            recovery.state(),
+            RecoveryState::Off {
+                attempt: 1,
+                until_ms: 1_100
+            }
+        );
+        assert_eq!(poll(&mut recovery, NORMAL, 600), OFF);
+        assert_eq!(poll(&mut recovery, NORMAL, 1_100), ON);
+
+        assert_eq!(poll(&mut recovery, SHORT, 1_200), OFF);
+        assert_eq!(
+            recovery.state(),
+            RecoveryState::Off {
+                attempt: 2,
+                until_ms: 3_200
+            }
+        );
+        // still shorted after the backoff
+        assert_eq!(poll(&mut recovery, SHORT, 3_200), OFF);
+        assert_eq!(poll(&mut recovery, NORMAL, 3_300), ON);
+
+        assert!(poll(&mut recovery, NORMAL, 13_300).is_empty());
+        assert_eq!(recovery.state(), RecoveryState::Normal);
+        assert_eq!(poll(&mut recovery, SHORT, 13_400), OFF);
+        assert_eq!(
+            recovery.state(),
+            RecoveryState::Off {
+                attempt: 1,
+                until_ms: 14_400
+            }
+        );
+    }
+
+    #[test]
+    fn escalation() {
+        let mut recovery = Recovery::new(RecoveryPolicy {
+            retries: 1,
+            retries_at_5v: 1,
+            backoff_ms: 100,
+            hard_reset: false,
+            ..Default::default()
+        });
+
+        poll(&mut recovery, SHORT, 0);
+        poll(&mut recovery, NORMAL, 100);
+        assert_eq!(
+            poll(&mut recovery, SHORT, 200),
+            RecoveryActions {
+                fast_charge_disabled: Some(true),
+                ..OFF
+            }
+        );
+        assert!(recovery.is_locked_to_5v());
+
+        poll(&mut recovery, NORMAL, 300);
+        assert_eq!(poll(&mut recovery, SHORT, 400), OFF);
+        assert_eq!(recovery.state(), RecoveryState::LatchedOff);
+        assert_eq!(poll(&mut recovery, NORMAL, 100_000), OFF);
+
+        assert_eq!(
+            recovery.reset(),
+            RecoveryActions {
+                buck_force_off: Some(false),
+                fast_charge_disabled: Some(false),
+                hard_reset: false,
+            }
+        );
+        assert_eq!(recovery.state(), RecoveryState::Normal);
+    }
+
+    #[test]
+    fn ignores_other_faults() {
+        let mut recovery = Recovery::new(RecoveryPolicy::default());
+
+        assert!(poll(&mut recovery, 0x14, 0).is_empty());
+        assert_eq!(poll(&mut recovery, 0x02, 100), OFF);
+    }
+
+    #[test]
+    fn backoff_cap() {
+        let mut recovery = Recovery::new(RecoveryPolicy {
+            retries: u8::MAX,
+            backoff_factor: 10,
+            max_backoff_ms: 5_000,
+            ..Default::default()
+        });
+
+        let mut now_ms = 0;
+        for _ in 0..4 {
+            poll(&mut recovery, SHORT, now_ms);
+            let RecoveryState::Off { until_ms, .. } = recovery.state() else {
+                panic!("{:?}", recovery.state());
+            };
+            assert!(until_ms - now_ms <= 5_000);
+            now_ms = until_ms;
+            poll(&mut recovery, NORMAL, now_ms);
+        }
+    }
+}
@@ -0,0 +1,236 @@
+use crate::{ModelError, Register};
+
+/// A value stored in a bit field of a register.
+pub trait Field: Sized {
+    /// Decode the field from its bits, already shifted down to bit 0.
+    /// Returns `None` if the encoding is not used by the chip.
+    fn from_bits(bits: u8) -> Option<Self>;
+
+    /// Encode the field, the register codec masks the result to the field width.
+    fn into_bits(self) -> u8;
+}
+
+impl Field for bool {
+    fn from_bits(bits: u8) -> Option<Self> {
+        Some(bits != 0)
+    }
+
+    fn into_bits(self) -> u8 {
+        self as u8
+    }
+}
+
+impl Field for u8 {
+    fn from_bits(bits: u8) -> Option<Self> {
+        Some(bits)
+    }
+
+    fn into_bits(self) -> u8 {
+        self
+    }
+}
+
+/// Implement [`Field`] for `#[repr(u8)]` enums deriving `TryFromPrimitive`.
+macro_rules! enum_fields {
+    ($($ty:ty),* $(,)?) => {
+        $(
+            impl $crate::register::Field for $ty {
+                fn from_bits(bits: u8) -> Option<Self> {
+                    <$ty>::try_from(bits).ok()
+                }
+
+                fn into_bits(self) -> u8 {
+                    self as u8
+                }
+            }
+        )*
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    ReadOnly,
+    ReadWrite,
+}
+
+/// Typed content of a register, generated by `registers!`.
+pub trait RegisterValue: Copy + TryFrom<u8, Error = ModelError> + Into<u8> {
+    /// Register address
+    const REGISTER: Register;
+    const ACCESS: Access;
+    /// Bits covered by the model. The others are reserved and are preserved on write.
+    const MASK: u8;
+    /// Register value after power-on reset, `None` for live status registers
+    /// and for the configuration registers whose default is not given by the register description.
+    const RESET: Option<u8>;
+    /// Name and mask of every field.
+    const FIELDS: &'static [(&'static str, u8)];
+}
+
+/// Mask of the field `[hi:lo]`, already shifted to its position in the register.
+pub(crate) const fn field_mask(hi: u8, lo: u8) -> u8 {
+    (((1u16 << (hi - lo + 1)) - 1) as u8) << lo
+}
+
+/// Describe the register map.
+///
+/// Every entry generates a variant of [`Register`], the struct holding the decoded fields,
+/// the `TryFrom<u8>` and `From<_> for u8` codecs and the [`RegisterValue`] impl.
+/// Fields are written as `name: Type [hi:lo]`, or `name: Type [bit]` for a single bit.
+/// `reset = _` marks a register without a known reset value.
+macro_rules! registers {
+    (@lo $hi:literal) => { $hi };
+    (@lo $hi:literal $lo:literal) => { $lo };
+    (@reset _) => { None };
+    (@reset $reset:literal) => { Some($reset) };
+
+    (
+        $(
+            $(#[$meta:meta])*
+            $variant:ident = $address:literal, $access:ident, reset = $reset:tt => $name:ident {
+                $(
+                    $(#[$field_meta:meta])*
+                    $field:ident: $ty:ty [$hi:literal $(: $lo:literal)?]
+                ),* $(,)?
+            }
+        )*
+    ) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        #[repr(u8)]
+        pub enum Register {
+            $($variant = $address,)*
+        }
+
+        impl Register {
+            pub const ALL: &'static [Register] = &[$(Register::$variant,)*];
+
+            /// Register at `address`, `None` if it is not modelled.
+            pub fn from_address(address: u8) -> Option<Self> {
+                match address {
+                    $($address => Some(Register::$variant),)*
+                    _ => None,
+                }
+            }
+
+            /// Value after power-on reset, see `RegisterValue::RESET`.
+            pub fn reset_value(self) -> Option<u8> {
+                match self {
+                    $(Register::$variant => registers!(@reset $reset),)*
+                }
+            }
+        }
+
+        $(
+            $(#[$meta])*
+            #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+            pub struct $name {
+                $(
+                    $(#[$field_meta])*
+                    pub $field: $ty,
+                )*
+            }
+
+            impl $crate::register::RegisterValue for $name {
+                const REGISTER: Register = Register::$variant;
+                const ACCESS: $crate::register::Access = $crate::register::Access::$access;
+                const MASK: u8 = 0 $(| $crate::register::field_mask($hi, registers!(@lo $hi $($lo)?)))*;
+                const RESET: Option<u8> = registers!(@reset $reset);
+                const FIELDS: &'static [(&'static str, u8)] = &[
+                    $((stringify!($field), $crate::register::field_mask($hi, registers!(@lo $hi $($lo)?))),)*
+                ];
+            }
+
+            impl TryFrom<u8> for $name {
+                type Error = $crate::error::ModelError;
+
+                fn try_from(value: u8) -> Result<Self, Self::Error> {
+                    Ok(Self {
+                        $(
+                            $field: <$ty as $crate::register::Field>::from_bits(
+                                (value & $crate::register::field_mask($hi, registers!(@lo $hi $($lo)?)))
+                                    >> registers!(@lo $hi $($lo)?),
+                            )
+                            .ok_or($crate::error::ModelError::InvalidRegisterValue {
+                                register: Register::$variant,
+                                value,
+                            })?,
+                        )*
+                    })
+                }
+            }
+
+            impl From<$name> for u8 {
+                fn from(value: $name) -> Self {
+                    0 $(
+                        | ((<$ty as $crate::register::Field>::into_bits(value.$field)
+                            << registers!(@lo $hi $($lo)?))
+                            & $crate::register::field_mask($hi, registers!(@lo $hi $($lo)?)))
+                    )*
+                }
+            }
+        )*
+
+        #[cfg(test)]
+        mod round_trip {
+            use super::*;
+
+            $(
+                #[test]
+                #[allow(non_snake_case)]
+                fn $name() {
+                    $crate::register::assert_round_trip::<$name>(
+                        Register::$variant,
+                        &[$($crate::register::field_mask($hi, registers!(@lo $hi $($lo)?))),*],
+                    );
+                }
+            )*
+        }
+    };
+}
+
+/// Check the generated codec of `T` against every possible register value.
+#[cfg(test)]
+pub(crate) fn assert_round_trip<T>(register: Register, field_masks: &[u8])
+where
+    T: RegisterValue + PartialEq + core::fmt::Debug,
+{
+    assert_eq!(T::REGISTER, register);
+
+    let mut covered = 0u8;
+    for mask in field_masks {
+        assert_eq!(covered & mask, 0, "overlapping fields in {:?}", register);
+        covered |= mask;
+    }
+    assert_eq!(covered, T::MASK);
+    assert!(T::FIELDS
+        .iter()
+        .map(|(_, mask)| *mask)
+        .eq(field_masks.iter().copied()));
+    if let Some(reset) = T::RESET {
+        assert!(
+            T::try_from(reset).is_ok(),
+            "{:?} reset value 0x{:02x} does not decode",
+            register,
+            reset
+        );
+    }
+
+    let mut decoded = 0;
+    for raw in 0..=u8::MAX {
+        let Ok(value) = T::try_from(raw) else {
+            continue;
+        };
+        decoded += 1;
+
+        let encoded: u8 = value.into();
+        assert_eq!(encoded & !T::MASK, 0, "{:?} writes reserved bits", register);
+        assert_eq!(
+            T::try_from(encoded).ok(),
+            Some(value),
+            "{:?} 0x{:02x}",
+            register,
+            raw
+        );
+    }
+    assert!(decoded > 0, "{:?} never decodes", register);
+}
@@ -0,0 +1,89 @@
+/// Fixed capacity FIFO, pushing to a full ring overwrites the oldest entry.
+#[derive(Debug, Clone)]
+pub(crate) struct Ring<T: Copy, const N: usize> {
+    entries: [Option<T>; N],
+    head: usize,
+    len: usize,
+}
+
+impl<T: Copy, const N: usize> Ring<T, N> {
+    pub const fn new() -> Self {
+        Self {
+            entries: [None; N],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    /// Returns `false` if an entry was lost, either overwritten or not stored at all.
+    pub fn push(&mut self, value: T) -> bool {
+        if N == 0 {
+            return false;
+        }
+
+        let lost = self.len == N;
+        if lost {
+            self.pop();
+        }
+
+        self.entries[(self.head + self.len) % N] = Some(value);
+        self.len += 1;
+        !lost
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+
+        let value = self.entries[self.head].take();
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        value
+    }
+
+    /// Oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        (0..self.len).filter_map(move |i| self.entries[(self.head + i) % N])
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overwrites_oldest() {
+        let mut ring = Ring::<u8, 3>::new();
+
+        assert!(ring.push(1));
+        assert!(ring.push(2));
+        assert!(ring.push(3));
+        assert!(!ring.push(4));
+
+        assert!(ring.iter().eq([2, 3, 4]));
+        assert_eq!(ring.pop(), Some(2));
+        assert!(ring.push(5));
+        assert!(ring.iter().eq([3, 4, 5]));
+        assert_eq!(ring.len(), 3);
+
+        ring.clear();
+        assert_eq!(ring.pop(), None);
+    }
+
+    #[test]
+    fn empty() {
+        let mut ring = Ring::<u8, 0>::new();
+
+        assert!(!ring.push(1));
+        assert_eq!(ring.pop(), None);
+    }
+}
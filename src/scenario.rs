@@ -0,0 +1,291 @@
+use crate::{
+    AbnormalKind, Cc, Milliamps, Millivolts, PdVersion, ProtocolIndication,
+    ProtocolIndicationResponse, ProtocolStatus, Sw3526Sim, VoltageStatus,
+};
+
+/// Change of the conditions around a [`Sw3526Sim`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimEvent {
+    /// A sink attaches, the output starts at 5 V without load
+    Attach(Cc),
+    /// The sink is removed, the output goes back to 5 V without load
+    Detach,
+    /// The sink negotiates `protocol` at `voltage`
+    Negotiate {
+        protocol: ProtocolIndication,
+        pd_version: PdVersion,
+        voltage: Millivolts,
+    },
+    /// The sink draws `current`
+    Load(Milliamps),
+    Vin(Millivolts),
+    Fault {
+        kind: AbnormalKind,
+        active: bool,
+    },
+    /// Power cycle of the chip, see [`Sw3526Sim::reset`]
+    Reset,
+}
+
+/// `event` happens at `at_ms` of the simulated time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step {
+    pub at_ms: u64,
+    pub event: SimEvent,
+}
+
+impl Step {
+    pub const fn new(at_ms: u64, event: SimEvent) -> Self {
+        Self { at_ms, event }
+    }
+}
+
+/// Plays a timeline of [`Step`]s on a [`Sw3526Sim`].
+///
+/// The simulated time only moves with the scenario, steps are applied when their time is reached
+/// so the driver observes them in order. Steps must be sorted by time.
+#[derive(Debug)]
+pub struct Scenario<'a> {
+    sim: &'a Sw3526Sim,
+    steps: &'a [Step],
+    next: usize,
+}
+
+impl<'a> Scenario<'a> {
+    pub fn new(sim: &'a Sw3526Sim, steps: &'a [Step]) -> Self {
+        debug_assert!(
+            steps.windows(2).all(|pair| pair[0].at_ms <= pair[1].at_ms),
+            "steps are not sorted by time"
+        );
+
+        let mut scenario = Self {
+            sim,
+            steps,
+            next: 0,
+        };
+        scenario.apply_due();
+        scenario
+    }
+
+    pub fn now_ms(&self) -> u64 {
+        self.sim.now_ms()
+    }
+
+    /// All the steps were applied.
+    pub fn is_finished(&self) -> bool {
+        self.next == self.steps.len()
+    }
+
+    /// Move the simulated time forward, applying the steps reached on the way.
+    pub fn advance_ms(&mut self, ms: u64) {
+        let until_ms = self.now_ms() + ms;
+        while let Some(step) = self.steps.get(self.next) {
+            if step.at_ms > until_ms {
+                break;
+            }
+            self.sim
+                .advance_ms(step.at_ms.saturating_sub(self.now_ms()));
+            self.apply_due();
+        }
+        self.sim.advance_ms(until_ms - self.now_ms());
+    }
+
+    /// Call `poll` every `period_ms` of simulated time until `until_ms`, with the current time.
+    /// Stops at the first error.
+    pub fn run<F, E>(&mut self, period_ms: u64, until_ms: u64, mut poll: F) -> Result<(), E>
+    where
+        F: FnMut(u64) -> Result<(), E>,
+    {
+        while self.now_ms() < until_ms {
+            self.advance_ms(period_ms.min(until_ms - self.now_ms()));
+            poll(self.now_ms())?;
+        }
+
+        Ok(())
+    }
+
+    fn apply_due(&mut self) {
+        while let Some(step) = self.steps.get(self.next) {
+            if step.at_ms > self.now_ms() {
+                break;
+            }
+            self.apply(step.event);
+            self.next += 1;
+        }
+    }
+
+    fn apply(&self, event: SimEvent) {
+        match event {
+            SimEvent::Attach(cc) => {
+                self.sim.attach(cc);
+                self.sim.set_vout(Millivolts(5000));
+                self.sim.set_iout(Milliamps::ZERO);
+            }
+            SimEvent::Detach => {
+                self.sim.detach();
+                self.sim.set_vout(Millivolts(5000));
+                self.sim.set_iout(Milliamps::ZERO);
+            }
+            SimEvent::Negotiate {
+                protocol,
+                pd_version,
+                voltage,
+            } => {
+                self.sim.set_protocol(ProtocolIndicationResponse {
+                    protocol_status: ProtocolStatus::OnLine,
+                    voltage_status: if voltage > Millivolts(5000) {
+                        VoltageStatus::ProtocolVoltage
+                    } else {
+                        VoltageStatus::_5V
+                    },
+                    pd_version,
+                    protocol,
+                });
+                self.sim.set_vout(voltage);
+            }
+            SimEvent::Load(current) => self.sim.set_iout(current),
+            SimEvent::Vin(vin) => self.sim.set_vin(vin),
+            SimEvent::Fault { kind, active } => self.sim.set_abnormal(kind, active),
+            SimEvent::Reset => self.sim.reset(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Derating, DeratingPolicy, FaultMonitor, Meter, Recovery, RecoveryPolicy, RecoveryState,
+        Session, Watts, SW3526,
+    };
+
+    const PHONE: [Step; 6] = [
+        Step::new(1_000, SimEvent::Attach(Cc::Cc2)),
+        Step::new(
+            1_500,
+            SimEvent::Negotiate {
+                protocol: ProtocolIndication::PdFix,
+                pd_version: PdVersion::PD3_0,
+                voltage: Millivolts(9000),
+            },
+        ),
+        Step::new(2_000, SimEvent::Load(Milliamps(2000))),
+        Step::new(
+            30_000,
+            SimEvent::Fault {
+                kind: AbnormalKind::OutputShortCircuit,
+                active: true,
+            },
+        ),
+        Step::new(
+            30_200,
+            SimEvent::Fault {
+                kind: AbnormalKind::OutputShortCircuit,
+                active: false,
+            },
+        ),
+        Step::new(60_000, SimEvent::Detach),
+    ];
+
+    #[test]
+    fn steps_in_order() {
+        let sim = Sw3526Sim::new();
+        let mut scenario = Scenario::new(&sim, &PHONE);
+
+        scenario.advance_ms(1_499);
+        assert_eq!(sim.register(crate::Register::CcStatus), 0x40);
+        assert_eq!(sim.register(crate::Register::ProtocolIndication), 0x00);
+
+        scenario.advance_ms(1);
+        assert_eq!(sim.register(crate::Register::ProtocolIndication), 0xe5);
+
+        scenario.advance_ms(100_000);
+        assert!(scenario.is_finished());
+        assert_eq!(scenario.now_ms(), 101_500);
+    }
+
+    #[test]
+    fn short_circuit_recovery() {
+        let sim = Sw3526Sim::new();
+        let mut scenario = Scenario::new(&sim, &PHONE);
+        let mut sw3526 = SW3526::new(sim.bus()).unlock().unwrap();
+        let mut meter = Meter::new();
+        let mut faults: FaultMonitor = FaultMonitor::default();
+        let mut recovery = Recovery::new(RecoveryPolicy::default());
+        let mut finished: Option<Session> = None;
+        let mut forced_off_ms = 0;
+
+        scenario
+            .run(100, 61_000, |now_ms| {
+                if let Some(session) = sw3526.update_meter(&mut meter, now_ms)? {
+                    finished = Some(session);
+                }
+                sw3526.poll_faults(&mut faults, now_ms)?;
+                sw3526.poll_recovery(&mut recovery, now_ms)?;
+                if sim.is_forced_off() {
+                    forced_off_ms += 100;
+                }
+                Ok::<_, crate::OperationError<_>>(())
+            })
+            .unwrap();
+
+        let stats = faults.stats(AbnormalKind::OutputShortCircuit);
+        assert_eq!(stats.count, 1);
+        assert_eq!(stats.first_seen_ms, Some(30_000));
+        assert!(!stats.active);
+
+        // off for the 1 s backoff, then the last force-off write runs out
+        assert_eq!(forced_off_ms, 1_900);
+        assert_eq!(sim.pd_commands_sent(), 1);
+        assert_eq!(recovery.state(), RecoveryState::Normal);
+
+        // 8.928 V × 2 A for 28 s before the short and about 28 s after
+        let session = finished.unwrap();
+        assert_eq!(session.started_at_ms, 1_000);
+        assert_eq!(session.delivered.milliwatt_hours(), 278);
+    }
+
+    #[test]
+    fn over_temperature_derating() {
+        const HOT: [Step; 3] = [
+            Step::new(0, SimEvent::Attach(Cc::Cc1)),
+            Step::new(
+                10_000,
+                SimEvent::Fault {
+                    kind: AbnormalKind::OverTemperatureAlarm,
+                    active: true,
+                },
+            ),
+            Step::new(
+                25_000,
+                SimEvent::Fault {
+                    kind: AbnormalKind::OverTemperatureAlarm,
+                    active: false,
+                },
+            ),
+        ];
+
+        let sim = Sw3526Sim::new();
+        let mut scenario = Scenario::new(&sim, &HOT);
+        let mut sw3526 = SW3526::new(sim.bus()).unlock().unwrap();
+        sw3526.set_output_limit_watts(Watts(65)).unwrap();
+        let mut derating = Derating::new(DeratingPolicy::default(), sw3526.capabilities()).unwrap();
+
+        let mut lowest = Watts(65);
+        scenario
+            .run(500, 120_000, |now_ms| {
+                sw3526.poll_derating(&mut derating, now_ms)?;
+                lowest = lowest.min(sw3526.get_limit_watts()?);
+                Ok::<_, crate::OperationError<_>>(())
+            })
+            .unwrap();
+
+        // 5 s per step down during the 15 s of alarm
+        assert_eq!(lowest, Watts(18));
+        assert_eq!(
+            sim.register(crate::Register::FastChargeConfig3) & 0x01,
+            0x01
+        );
+        assert_eq!(sw3526.get_limit_watts().unwrap(), Watts(65));
+    }
+}
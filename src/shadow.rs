@@ -0,0 +1,188 @@
+use crate::Register;
+
+/// Writable configuration registers kept in the shadow.
+pub(crate) const SHADOWED_REGISTERS: [Register; 13] = [
+    Register::BuckForceOff,
+    Register::AdcConfig,
+    Register::PowerCommandRequest,
+    Register::FastChargeConfig6,
+    Register::FastChargeConfig5,
+    Register::PowerConfig,
+    Register::FastChargeConfig0,
+    Register::FastChargeConfig1,
+    Register::FastChargeConfig2,
+    Register::FastChargeConfig3,
+    Register::FastChargeConfig4,
+    Register::VidConfig0,
+    Register::VidConfig1,
+];
+
+const LEN: usize = SHADOWED_REGISTERS.len();
+
+/// Registers ignoring writes until the unlock sequence is sent.
+fn is_write_protected(register: Register) -> bool {
+    matches!(register as u8, 0x13 | 0x70 | 0x71 | 0xa0..=0xbf)
+}
+
+/// Bits the chip clears by itself, they are kept cleared in the shadow.
+fn self_clearing_bits(register: Register) -> u8 {
+    match register {
+        // reg0x13 forces the buck off for one second, reg0x70 sends the PD command
+        Register::BuckForceOff | Register::PowerCommandRequest => 0x80,
+        _ => 0x00,
+    }
+}
+
+/// Copy of the writable configuration registers.
+///
+/// A register is valid once read from or written to the chip. Staged values are dirty
+/// until written. Writes to the protected registers are only kept once the chip is known
+/// to be unlocked. The shadow does not see the chip reset, it has to be invalidated then.
+#[derive(Debug, Clone)]
+pub(crate) struct Shadow {
+    /// Value known to be in the chip
+    chip: [u8; LEN],
+    /// Value to write, equal to `chip` unless dirty
+    staged: [u8; LEN],
+    valid: u16,
+    dirty: u16,
+    /// Writes to the protected registers take
+    unlocked: bool,
+}
+
+impl Shadow {
+    pub const fn new() -> Self {
+        Self {
+            chip: [0; LEN],
+            staged: [0; LEN],
+            valid: 0,
+            dirty: 0,
+            unlocked: false,
+        }
+    }
+
+    fn slot(register: Register) -> Option<usize> {
+        SHADOWED_REGISTERS.iter().position(|r| *r == register)
+    }
+
+    /// Value of `register` including the staged changes, `None` if not valid or not shadowed.
+    pub fn get(&self, register: Register) -> Option<u8> {
+        let slot = Self::slot(register)?;
+        (self.valid & (1 << slot) != 0).then_some(self.staged[slot])
+    }
+
+    /// Record a value read from or written to the chip, dropping staged changes.
+    pub fn store(&mut self, register: Register, value: u8) {
+        let Some(slot) = Self::slot(register) else {
+            return;
+        };
+        let value = value & !self_clearing_bits(register);
+        self.chip[slot] = value;
+        self.staged[slot] = value;
+        self.valid |= 1 << slot;
+        self.dirty &= !(1 << slot);
+    }
+
+    /// Record a value written to the chip. A protected register is read again instead
+    /// while the chip is not known to be unlocked, as it may have ignored the write.
+    pub fn store_write(&mut self, register: Register, value: u8) {
+        if self.unlocked || !is_write_protected(register) {
+            self.store(register, value);
+        } else if let Some(slot) = Self::slot(register) {
+            self.valid &= !(1 << slot);
+            self.dirty &= !(1 << slot);
+        }
+    }
+
+    /// The unlock sequence was acknowledged or a write to a protected register was seen to take.
+    pub fn set_unlocked(&mut self, unlocked: bool) {
+        self.unlocked = unlocked;
+    }
+
+    /// Change the value to write with the next flush, the register must be valid.
+    /// Returns `false` if it is not.
+    pub fn stage(&mut self, register: Register, value: u8) -> bool {
+        let Some(slot) = Self::slot(register).filter(|slot| self.valid & (1 << slot) != 0) else {
+            return false;
+        };
+        self.staged[slot] = value;
+        if value == self.chip[slot] {
+            self.dirty &= !(1 << slot);
+        } else {
+            self.dirty |= 1 << slot;
+        }
+        true
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty != 0
+    }
+
+    /// Registers with a staged value differing from the chip.
+    pub fn dirty(&self) -> impl Iterator<Item = (Register, u8)> + '_ {
+        SHADOWED_REGISTERS
+            .iter()
+            .enumerate()
+            .filter(|(slot, _)| self.dirty & (1 << slot) != 0)
+            .map(|(slot, register)| (*register, self.staged[slot]))
+    }
+
+    pub fn invalidate(&mut self) {
+        self.valid = 0;
+        self.dirty = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_and_dirty() {
+        let mut shadow = Shadow::new();
+
+        assert_eq!(shadow.get(Register::PowerConfig), None);
+        assert!(!shadow.stage(Register::PowerConfig, 30));
+        assert_eq!(shadow.get(Register::CcStatus), None);
+
+        shadow.store(Register::PowerConfig, 65 & 0x3f);
+        shadow.store(Register::FastChargeConfig4, 0x00);
+        assert!(shadow.stage(Register::PowerConfig, 30));
+        assert!(shadow.stage(Register::FastChargeConfig4, 0x04));
+        assert!(shadow.stage(Register::FastChargeConfig4, 0x00));
+        assert_eq!(shadow.get(Register::PowerConfig), Some(30));
+        assert_eq!(
+            shadow.dirty().collect::<Vec<_>>(),
+            [(Register::PowerConfig, 30)]
+        );
+
+        shadow.store(Register::PowerConfig, 30);
+        assert!(!shadow.is_dirty());
+
+        shadow.invalidate();
+        assert_eq!(shadow.get(Register::PowerConfig), None);
+    }
+
+    #[test]
+    fn writes_while_locked() {
+        let mut shadow = Shadow::new();
+
+        shadow.store(Register::PowerConfig, 65 & 0x3f);
+        shadow.store_write(Register::PowerConfig, 30);
+        shadow.store_write(Register::AdcConfig, 0x03);
+        assert_eq!(shadow.get(Register::PowerConfig), None);
+        assert_eq!(shadow.get(Register::AdcConfig), Some(0x03));
+
+        shadow.set_unlocked(true);
+        shadow.store_write(Register::PowerConfig, 30);
+        assert_eq!(shadow.get(Register::PowerConfig), Some(30));
+    }
+
+    #[test]
+    fn self_clearing() {
+        let mut shadow = Shadow::new();
+
+        shadow.store(Register::PowerCommandRequest, 0x81);
+        assert_eq!(shadow.get(Register::PowerCommandRequest), Some(0x01));
+    }
+}
@@ -0,0 +1,570 @@
+use core::cell::RefCell;
+
+use embedded_hal::i2c::{ErrorKind, ErrorType, I2c, NoAcknowledgeSource, Operation};
+
+use crate::{
+    AbnormalKind, AdcConfig, Cc, Chip, Milliamps, Millivolts, PowerConfig,
+    ProtocolIndicationResponse, Register, Sw3526, Watts,
+};
+
+const UNLOCK_SEQUENCE: [u8; 3] = [0x20, 0x40, 0x80];
+
+/// reg0x13 bit 7 turns the buck off for this long.
+pub const FORCE_OFF_MS: u64 = 1000;
+
+/// Behavioural model of a SW3526 on an I2C bus, for tests without hardware.
+///
+/// The register file answers at the SW3526 address through [`Sw3526Sim::bus`].
+/// reg0x13, reg0x70~71 and reg0xA0~BF ignore writes until the unlock sequence is written to reg0x12,
+/// read-only registers ignore writes. Bursts auto-increment the register address.
+/// The status and ADC registers are derived from the conditions injected by the test,
+/// the output reads 0 while the buck is forced off, shorted or shut down by over-temperature.
+/// Time only moves with [`Sw3526Sim::advance_ms`].
+#[derive(Debug)]
+pub struct Sw3526Sim {
+    state: RefCell<SimState>,
+}
+
+#[derive(Debug, Clone)]
+struct SimState {
+    registers: [u8; 256],
+    pointer: u8,
+    unlock_step: usize,
+    unlocked: bool,
+    responding: bool,
+    now_ms: u64,
+    force_off_until_ms: Option<u64>,
+    pd_commands_sent: u32,
+    vin: Millivolts,
+    vout: Millivolts,
+    iout: Milliamps,
+    cc: Option<Cc>,
+    rset_limit: Watts,
+}
+
+impl SimState {
+    fn new() -> Self {
+        let mut registers = [0; 256];
+        // registers without a known reset value start at 0, set them with `Sw3526Sim::set_register`
+        for &register in Register::ALL {
+            if let Some(value) = register.reset_value() {
+                registers[register as usize] = value;
+            }
+        }
+
+        Self {
+            registers,
+            pointer: 0,
+            unlock_step: 0,
+            unlocked: false,
+            responding: true,
+            now_ms: 0,
+            force_off_until_ms: None,
+            pd_commands_sent: 0,
+            vin: Millivolts::ZERO,
+            vout: Millivolts::ZERO,
+            iout: Milliamps::ZERO,
+            cc: None,
+            rset_limit: Watts(65),
+        }
+    }
+
+    fn forced_off(&self) -> bool {
+        self.force_off_until_ms.is_some()
+    }
+
+    /// The buck is forced off or stopped by a short circuit or over-temperature shutdown.
+    fn buck_off(&self) -> bool {
+        // reg0x0B bit 1 and 0
+        self.forced_off() || self.registers[Register::AbnormalCase as usize] & 0x03 != 0
+    }
+
+    fn output(&self) -> (Millivolts, Milliamps) {
+        if self.buck_off() {
+            (Millivolts::ZERO, Milliamps::ZERO)
+        } else {
+            (self.vout, self.iout)
+        }
+    }
+
+    fn limit(&self) -> Watts {
+        // reg0xAB bit 0 selects reg0xA7 over Rset
+        if self.registers[Register::FastChargeConfig3 as usize] & 0x01 != 0 {
+            PowerConfig::try_from(self.registers[Register::PowerConfig as usize])
+                .map(PowerConfig::to_watts)
+                .unwrap_or(self.rset_limit)
+        } else {
+            self.rset_limit
+        }
+    }
+
+    /// 12-bit ADC value of `channel`.
+    fn adc(&self, channel: AdcConfig) -> u16 {
+        let (vout, iout) = self.output();
+        let raw = match channel {
+            AdcConfig::Vin => self.vin.0 / 10,
+            AdcConfig::Vout => vout.0 / 6,
+            // 2.5 mA per LSB
+            AdcConfig::Iout => iout.0 * 2 / 5,
+        };
+        raw.min(0xfff) as u16
+    }
+
+    fn read(&self, address: u8) -> u8 {
+        let Some(register) = Register::from_address(address) else {
+            return self.registers[address as usize];
+        };
+
+        let (vout, _) = self.output();
+        let set_point = (vout.0 / 10).min(0xfff) as u16;
+        match register {
+            Register::BuckOutputVoltageHigh8b => (set_point >> 4) as u8,
+            Register::BuckOutputVoltageLow4b => ((set_point & 0x0f) as u8) << 4,
+            Register::SystemStatus => {
+                let buck_on = !self.buck_off();
+                let port_on = buck_on && self.cc.is_some();
+                ((port_on as u8) << 1) | buck_on as u8
+            }
+            Register::AdcVinData => (self.adc(AdcConfig::Vin) >> 4) as u8,
+            Register::AdcVoutData => (self.adc(AdcConfig::Vout) >> 4) as u8,
+            Register::AdcIoutData => (self.adc(AdcConfig::Iout) >> 4) as u8,
+            Register::AdcDataHigh8b | Register::AdcDataLow4b => {
+                let raw = AdcConfig::try_from(self.registers[Register::AdcConfig as usize] & 0x03)
+                    .map(|channel| self.adc(channel))
+                    .unwrap_or(0);
+                if register == Register::AdcDataHigh8b {
+                    (raw >> 4) as u8
+                } else {
+                    (raw & 0x0f) as u8
+                }
+            }
+            Register::PowerStatus => self.limit().0 & 0x7f,
+            Register::CcStatus => match self.cc {
+                Some(Cc::Cc1) => 0x80,
+                Some(Cc::Cc2) => 0x40,
+                None => 0x00,
+            },
+            _ => self.registers[address as usize],
+        }
+    }
+
+    fn write(&mut self, address: u8, value: u8) {
+        match address {
+            0x12 => {
+                self.registers[address as usize] = value;
+                if value == UNLOCK_SEQUENCE[self.unlock_step] {
+                    self.unlock_step += 1;
+                } else {
+                    self.unlock_step = (value == UNLOCK_SEQUENCE[0]) as usize;
+                }
+                if self.unlock_step == UNLOCK_SEQUENCE.len() {
+                    self.unlocked = true;
+                    self.unlock_step = 0;
+                }
+            }
+            0x13 if self.unlocked => {
+                self.registers[address as usize] = value;
+                if value & 0x80 != 0 {
+                    self.force_off_until_ms = Some(self.now_ms + FORCE_OFF_MS);
+                }
+            }
+            0x70 if self.unlocked => {
+                // the send bit is cleared by the chip once the command is sent
+                if value & 0x80 != 0 {
+                    self.pd_commands_sent += 1;
+                }
+                self.registers[address as usize] = value & 0x7f;
+            }
+            0x3a => self.registers[address as usize] = value,
+            0x71 | 0xa0..=0xbf if self.unlocked => self.registers[address as usize] = value,
+            _ => {}
+        }
+    }
+
+    fn advance_ms(&mut self, ms: u64) {
+        self.now_ms += ms;
+        if let Some(until_ms) = self.force_off_until_ms {
+            if self.now_ms >= until_ms {
+                self.force_off_until_ms = None;
+                self.registers[Register::BuckForceOff as usize] &= 0x7f;
+            }
+        }
+    }
+}
+
+impl Default for Sw3526Sim {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Sw3526Sim {
+    /// A chip after power-on: locked, detached, no input.
+    pub fn new() -> Self {
+        Self {
+            state: RefCell::new(SimState::new()),
+        }
+    }
+
+    /// Handle implementing `I2c`, to give to the driver.
+    pub fn bus(&self) -> SimBus<'_> {
+        SimBus { sim: self }
+    }
+
+    /// Power cycle: the registers go back to their reset value, or 0 when it is not known,
+    /// and the chip is locked again.
+    /// Injected conditions and time are kept.
+    pub fn reset(&self) {
+        let mut state = self.state.borrow_mut();
+        let fresh = SimState::new();
+        state.registers = fresh.registers;
+        state.unlock_step = 0;
+        state.unlocked = false;
+        state.force_off_until_ms = None;
+    }
+
+    /// Move the simulated time forward.
+    pub fn advance_ms(&self, ms: u64) {
+        self.state.borrow_mut().advance_ms(ms);
+    }
+
+    pub fn now_ms(&self) -> u64 {
+        self.state.borrow().now_ms
+    }
+
+    /// The unlock sequence was written since the last reset.
+    pub fn is_unlocked(&self) -> bool {
+        self.state.borrow().unlocked
+    }
+
+    /// The buck is forced off through reg0x13.
+    pub fn is_forced_off(&self) -> bool {
+        self.state.borrow().forced_off()
+    }
+
+    /// Commands sent through reg0x70.
+    pub fn pd_commands_sent(&self) -> u32 {
+        self.state.borrow().pd_commands_sent
+    }
+
+    /// Stop acknowledging the address, as a chip without supply.
+    pub fn set_responding(&self, responding: bool) {
+        self.state.borrow_mut().responding = responding;
+    }
+
+    pub fn set_vin(&self, vin: Millivolts) {
+        self.state.borrow_mut().vin = vin;
+    }
+
+    /// Output voltage, also reported as the buck set point in reg0x03~04.
+    pub fn set_vout(&self, vout: Millivolts) {
+        self.state.borrow_mut().vout = vout;
+    }
+
+    pub fn set_iout(&self, iout: Milliamps) {
+        self.state.borrow_mut().iout = iout;
+    }
+
+    /// Limit set by the Rset resistance, reported in reg0x68 unless reg0xAB selects reg0xA7.
+    pub fn set_rset_limit(&self, limit: Watts) {
+        self.state.borrow_mut().rset_limit = limit;
+    }
+
+    /// A sink attaches on `cc`.
+    pub fn attach(&self, cc: Cc) {
+        self.state.borrow_mut().cc = Some(cc);
+    }
+
+    /// The sink is removed, the protocol indication is cleared.
+    pub fn detach(&self) {
+        let mut state = self.state.borrow_mut();
+        state.cc = None;
+        state.registers[Register::ProtocolIndication as usize] = 0;
+    }
+
+    pub fn set_protocol(&self, protocol: ProtocolIndicationResponse) {
+        self.state.borrow_mut().registers[Register::ProtocolIndication as usize] = protocol.into();
+    }
+
+    /// Raise or clear a condition of reg0x0B.
+    pub fn set_abnormal(&self, kind: AbnormalKind, active: bool) {
+        let bit = match kind {
+            AbnormalKind::VinOvp => 4,
+            AbnormalKind::OverTemperatureAlarm => 2,
+            AbnormalKind::OverTemperatureShutdown => 1,
+            AbnormalKind::OutputShortCircuit => 0,
+        };
+        let mut state = self.state.borrow_mut();
+        let abnormal = &mut state.registers[Register::AbnormalCase as usize];
+        if active {
+            *abnormal |= 1 << bit;
+        } else {
+            *abnormal &= !(1 << bit);
+        }
+    }
+
+    /// Value the chip returns for `register`.
+    pub fn register(&self, register: Register) -> u8 {
+        self.state.borrow().read(register as u8)
+    }
+
+    /// Store a raw value, bypassing the write protection.
+    /// Registers derived from the injected conditions keep reporting those.
+    pub fn set_register(&self, register: Register, value: u8) {
+        self.state.borrow_mut().registers[register as usize] = value;
+    }
+}
+
+/// I2C bus with a [`Sw3526Sim`] on it.
+#[derive(Debug, Clone, Copy)]
+pub struct SimBus<'a> {
+    sim: &'a Sw3526Sim,
+}
+
+impl SimBus<'_> {
+    fn run(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<(), ErrorKind> {
+        let mut state = self.sim.state.borrow_mut();
+        if address != Sw3526::ADDRESS || !state.responding {
+            return Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address));
+        }
+
+        for operation in operations {
+            match operation {
+                Operation::Write(bytes) => {
+                    let Some((&register, values)) = bytes.split_first() else {
+                        continue;
+                    };
+                    state.pointer = register;
+                    for &value in values {
+                        let pointer = state.pointer;
+                        state.write(pointer, value);
+                        state.pointer = pointer.wrapping_add(1);
+                    }
+                }
+                Operation::Read(buffer) => {
+                    for byte in buffer.iter_mut() {
+                        *byte = state.read(state.pointer);
+                        state.pointer = state.pointer.wrapping_add(1);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ErrorType for SimBus<'_> {
+    type Error = ErrorKind;
+}
+
+impl I2c for SimBus<'_> {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.run(address, operations)
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::i2c::I2c for SimBus<'_> {
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.run(address, operations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        AdcData, Coherence, Microamps, PortStatus, ProtocolIndication, ProtocolStatus, SW3526,
+    };
+    use embedded_hal_mock::eh1::delay::NoopDelay;
+
+    #[test]
+    fn write_protection() {
+        let sim = Sw3526Sim::new();
+        let mut sw3526 = SW3526::new(sim.bus());
+
+        assert!(!sw3526.is_writable().unwrap());
+        let mut sw3526 = sw3526.assume_unlocked();
+        sw3526.set_output_limit_watts(Watts(30)).unwrap();
+        assert_eq!(sim.register(Register::PowerConfig), 0x00);
+        // the ignored write is not kept in the shadow
+        assert_eq!(sw3526.get_output_limit_watts().unwrap(), Watts(64));
+
+        // the ADC selection is not protected
+        sw3526.set_adc_config(AdcConfig::Iout).unwrap();
+        assert_eq!(sim.register(Register::AdcConfig), 0x03);
+
+        let mut sw3526 = sw3526.lock().unlock().unwrap();
+        assert!(sim.is_unlocked());
+        // the probe restores the PD command and sends none
+        assert!(sw3526.is_writable().unwrap());
+        assert_eq!(sim.register(Register::PowerCommandRequest), 0x00);
+        assert_eq!(sim.pd_commands_sent(), 0);
+        sw3526.set_output_limit_watts(Watts(30)).unwrap();
+        assert_eq!(sim.register(Register::PowerConfig), 30);
+        assert_eq!(sw3526.get_output_limit_watts().unwrap(), Watts(30));
+
+        sim.reset();
+        sw3526.invalidate();
+        sw3526.set_output_limit_watts(Watts(45)).unwrap();
+        assert_eq!(sim.register(Register::PowerConfig), 0x00);
+        assert_eq!(sw3526.get_output_limit_watts().unwrap(), Watts(64));
+        assert!(!sw3526.is_writable().unwrap());
+    }
+
+    #[test]
+    fn interrupted_unlock_sequence() {
+        let sim = Sw3526Sim::new();
+        let mut bus = sim.bus();
+
+        bus.write(Sw3526::ADDRESS, &[0x12, 0x20]).unwrap();
+        bus.write(Sw3526::ADDRESS, &[0x12, 0x00]).unwrap();
+        bus.write(Sw3526::ADDRESS, &[0x12, 0x40]).unwrap();
+        bus.write(Sw3526::ADDRESS, &[0x12, 0x80]).unwrap();
+        assert!(!sim.is_unlocked());
+    }
+
+    #[test]
+    fn pd_command_auto_clear() {
+        let sim = Sw3526Sim::new();
+        let mut sw3526 = SW3526::new(sim.bus()).unlock().unwrap();
+
+        sw3526.send_pd_hard_reset().unwrap();
+        assert_eq!(sim.pd_commands_sent(), 1);
+        assert!(!sw3526.get_power_command_request().unwrap().send_enabled);
+    }
+
+    #[test]
+    fn adc_data_select() {
+        let sim = Sw3526Sim::new();
+        sim.set_vin(Millivolts(20000));
+        sim.set_vout(Millivolts(9000));
+        sim.set_iout(Milliamps(2000));
+        let mut sw3526 = SW3526::new(sim.bus());
+
+        let measurements = sw3526.measure_all(&mut NoopDelay).unwrap();
+        assert_eq!(measurements.vin, Millivolts(20000));
+        assert_eq!(measurements.vout, Millivolts(9000));
+        assert_eq!(measurements.iout, Microamps(2000000));
+
+        sw3526.set_adc_config(AdcConfig::Vout).unwrap();
+        assert_eq!(
+            sw3526.get_adc_data().unwrap(),
+            Some(AdcData::Vout(Millivolts(9000)))
+        );
+        assert_eq!(
+            sw3526.get_buck_output_millivolts().unwrap(),
+            Millivolts(9000)
+        );
+    }
+
+    #[test]
+    fn snapshot_follows_injected_state() {
+        let sim = Sw3526Sim::new();
+        sim.set_vout(Millivolts(9000));
+        sim.set_iout(Milliamps(2000));
+        let mut sw3526 = SW3526::new(sim.bus());
+
+        let snapshot = sw3526.read_snapshot().unwrap();
+        assert!(!snapshot.cc_status.is_attached());
+        assert_eq!(snapshot.limit, Watts(65));
+
+        sim.attach(Cc::Cc2);
+        sim.set_protocol(0xa5.try_into().unwrap());
+        sim.set_abnormal(AbnormalKind::OverTemperatureAlarm, true);
+        let snapshot = sw3526.read_snapshot().unwrap();
+        assert_eq!(snapshot.coherence, Coherence::Stable);
+        assert_eq!(snapshot.cc_status.cc(), Some(Cc::Cc2));
+        assert_eq!(snapshot.system_status.port_status, PortStatus::On);
+        assert_eq!(snapshot.protocol.protocol, ProtocolIndication::PdFix);
+        assert_eq!(snapshot.protocol.protocol_status, ProtocolStatus::OnLine);
+        assert!(snapshot
+            .abnormal_case
+            .is_active(AbnormalKind::OverTemperatureAlarm));
+        assert_eq!(snapshot.output_current, Milliamps(2000));
+
+        sim.detach();
+        assert_eq!(
+            sw3526.get_protocol().unwrap().protocol,
+            ProtocolIndication::Unknown
+        );
+    }
+
+    #[test]
+    fn buck_force_off() {
+        let sim = Sw3526Sim::new();
+        sim.set_vout(Millivolts(5000));
+        sim.attach(Cc::Cc1);
+        let mut sw3526 = SW3526::new(sim.bus()).unlock().unwrap();
+
+        sw3526
+            .modify_buck_force_off(|config| {
+                config.force_off = crate::BuckForceOff::TurnOffOneSecond
+            })
+            .unwrap();
+        assert_eq!(
+            sw3526.get_adc_output_millivolts().unwrap(),
+            Millivolts::ZERO
+        );
+        assert_eq!(
+            sw3526.get_system_status().unwrap().port_status,
+            PortStatus::Off
+        );
+
+        sim.advance_ms(FORCE_OFF_MS);
+        assert!(!sim.is_forced_off());
+        assert_eq!(
+            sw3526.get_buck_force_off().unwrap().force_off,
+            crate::BuckForceOff::Nothing
+        );
+        assert_eq!(
+            sw3526.get_system_status().unwrap().port_status,
+            PortStatus::On
+        );
+    }
+
+    #[test]
+    fn power_limit_source() {
+        let sim = Sw3526Sim::new();
+        sim.set_rset_limit(Watts(45));
+        let mut sw3526 = SW3526::new(sim.bus()).unlock().unwrap();
+
+        sw3526.set_output_limit_watts(Watts(30)).unwrap();
+        assert_eq!(sw3526.get_limit_watts().unwrap(), Watts(45));
+
+        sw3526
+            .modify_fast_charge_config_3(|config| {
+                config.power_setting_method = crate::PowerSettingMethod::Register
+            })
+            .unwrap();
+        assert_eq!(sw3526.get_limit_watts().unwrap(), Watts(30));
+    }
+
+    #[test]
+    fn no_answer() {
+        let sim = Sw3526Sim::new();
+        let mut sw3526 = SW3526::new(sim.bus());
+
+        // the ADC selection written by the probe is restored
+        assert!(sw3526.probe().is_ok());
+        assert_eq!(sim.register(Register::AdcConfig), 0x00);
+
+        sim.set_responding(false);
+        assert!(sw3526.probe().is_err());
+
+        let mut bus = sim.bus();
+        assert_eq!(
+            bus.write(0x3d, &[0x01]),
+            Err(ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address))
+        );
+    }
+}
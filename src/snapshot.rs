@@ -0,0 +1,79 @@
+use crate::{
+    AbnormalCaseResponse, AdcIoutData, AdcVinData, AdcVoutData, BuckOutputCurrentLimit,
+    BuckOutputVoltageHigh8b, BuckOutputVoltageLow4b, CcStatus, Milliamps, Millivolts, ModelError,
+    PowerStatus, ProtocolIndicationResponse, SystemStatusResponse, Watts,
+};
+
+/// How well the values of a [`Sw3526Snapshot`] describe a single state of the chip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Coherence {
+    /// The status registers read again at the end of the snapshot were unchanged.
+    Stable,
+    /// The status registers changed while the snapshot was taken, values may mix both states.
+    Changed,
+}
+
+/// All live status registers, read with `SW3526::read_snapshot`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sw3526Snapshot {
+    pub protocol: ProtocolIndicationResponse,
+    pub system_status: SystemStatusResponse,
+    pub abnormal_case: AbnormalCaseResponse,
+    pub cc_status: CcStatus,
+    /// Buck output voltage set point
+    pub buck_output: Millivolts,
+    pub buck_output_limit: Milliamps,
+    pub input_voltage: Millivolts,
+    pub output_voltage: Millivolts,
+    pub output_current: Milliamps,
+    /// Power value set by Rset or reg0xA7
+    pub limit: Watts,
+    pub coherence: Coherence,
+}
+
+/// Raw register blocks of a snapshot, in bus order.
+pub(crate) struct SnapshotRegisters {
+    /// reg0x03~07
+    pub status: [u8; 5],
+    /// reg0x0B
+    pub abnormal: [u8; 1],
+    /// reg0x30~33, reg0x32 is not used
+    pub adc: [u8; 4],
+    /// reg0x68~69
+    pub power: [u8; 2],
+    /// reg0x03~07 read again after everything else
+    pub status_again: [u8; 5],
+    /// reg0x68~69 read again after everything else
+    pub power_again: [u8; 2],
+}
+
+impl TryFrom<&SnapshotRegisters> for Sw3526Snapshot {
+    type Error = ModelError;
+
+    fn try_from(value: &SnapshotRegisters) -> Result<Self, Self::Error> {
+        let [vout_high, vout_low, current_limit, protocol, system_status] = value.status;
+        let [vin, vout, _, iout] = value.adc;
+        let [limit, cc_status] = value.power;
+
+        let stable = value.status == value.status_again && value.power == value.power_again;
+
+        Ok(Self {
+            protocol: protocol.try_into()?,
+            system_status: system_status.try_into()?,
+            abnormal_case: value.abnormal[0].try_into()?,
+            cc_status: cc_status.try_into()?,
+            buck_output: BuckOutputVoltageHigh8b::try_from(vout_high)?
+                .millivolts(BuckOutputVoltageLow4b::try_from(vout_low)?),
+            buck_output_limit: BuckOutputCurrentLimit::try_from(current_limit)?.milliamps(),
+            input_voltage: AdcVinData::try_from(vin)?.millivolts(),
+            output_voltage: AdcVoutData::try_from(vout)?.millivolts(),
+            output_current: AdcIoutData::try_from(iout)?.milliamps(),
+            limit: PowerStatus::try_from(limit)?.watts(),
+            coherence: if stable {
+                Coherence::Stable
+            } else {
+                Coherence::Changed
+            },
+        })
+    }
+}
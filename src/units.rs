@@ -0,0 +1,164 @@
+use core::fmt;
+
+macro_rules! unit {
+    ($(#[$meta:meta])* $name:ident($inner:ty), $symbol:literal) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+        pub struct $name(pub $inner);
+
+        impl $name {
+            pub const ZERO: Self = Self(0);
+            pub const MAX: Self = Self(<$inner>::MAX);
+
+            pub const fn new(value: $inner) -> Self {
+                Self(value)
+            }
+
+            pub const fn get(self) -> $inner {
+                self.0
+            }
+
+            pub fn checked_add(self, rhs: Self) -> Option<Self> {
+                self.0.checked_add(rhs.0).map(Self)
+            }
+
+            pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+                self.0.checked_sub(rhs.0).map(Self)
+            }
+
+            pub fn checked_mul(self, rhs: $inner) -> Option<Self> {
+                self.0.checked_mul(rhs).map(Self)
+            }
+
+            pub fn checked_div(self, rhs: $inner) -> Option<Self> {
+                self.0.checked_div(rhs).map(Self)
+            }
+
+            pub fn saturating_add(self, rhs: Self) -> Self {
+                Self(self.0.saturating_add(rhs.0))
+            }
+
+            pub fn saturating_sub(self, rhs: Self) -> Self {
+                Self(self.0.saturating_sub(rhs.0))
+            }
+        }
+
+        #[cfg(feature = "float")]
+        impl $name {
+            pub fn to_f32(self) -> f32 {
+                self.0 as f32
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{} {}", self.0, $symbol)
+            }
+        }
+    };
+}
+
+unit!(Millivolts(u32), "mV");
+unit!(Milliamps(u32), "mA");
+unit!(
+    /// Resolution of the 12-bit Iout ADC data, whose LSB is 2.5 mA.
+    Microamps(u32),
+    "uA"
+);
+unit!(Milliwatts(u32), "mW");
+unit!(Watts(u8), "W");
+
+impl Millivolts {
+    /// Power delivered at this voltage and `current`, rounded down to the milliwatt.
+    pub fn checked_power(self, current: Milliamps) -> Option<Milliwatts> {
+        let microwatts = self.0 as u64 * current.0 as u64;
+        u32::try_from(microwatts / 1000).ok().map(Milliwatts)
+    }
+}
+
+impl Milliwatts {
+    /// Rounded down to the watt, `None` if it does not fit in [`Watts`].
+    pub fn checked_to_watts(self) -> Option<Watts> {
+        u8::try_from(self.0 / 1000).ok().map(Watts)
+    }
+
+    /// Current drawn at `voltage` for this power, rounded down to the milliamp.
+    pub fn checked_current(self, voltage: Millivolts) -> Option<Milliamps> {
+        let microwatts = self.0 as u64 * 1000;
+        microwatts
+            .checked_div(voltage.0 as u64)
+            .and_then(|milliamps| u32::try_from(milliamps).ok())
+            .map(Milliamps)
+    }
+}
+
+impl Microamps {
+    /// Rounded down to the milliamp.
+    pub fn to_milliamps(self) -> Milliamps {
+        Milliamps(self.0 / 1000)
+    }
+
+    #[cfg(feature = "float")]
+    pub fn to_milliamps_f32(self) -> f32 {
+        self.0 as f32 / 1000.0
+    }
+}
+
+impl Milliamps {
+    pub fn checked_to_microamps(self) -> Option<Microamps> {
+        self.0.checked_mul(1000).map(Microamps)
+    }
+}
+
+impl From<Watts> for Milliwatts {
+    fn from(value: Watts) -> Self {
+        Self(value.0 as u32 * 1000)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn power() {
+        assert_eq!(
+            Millivolts(20000).checked_power(Milliamps(3250)),
+            Some(Milliwatts(65000))
+        );
+        assert_eq!(
+            Millivolts(u32::MAX).checked_power(Milliamps(u32::MAX)),
+            None
+        );
+        assert_eq!(
+            Milliwatts(65000).checked_current(Millivolts(20000)),
+            Some(Milliamps(3250))
+        );
+        assert_eq!(Milliwatts(65000).checked_current(Millivolts(0)), None);
+    }
+
+    #[test]
+    fn watts() {
+        assert_eq!(Milliwatts::from(Watts(71)), Milliwatts(71000));
+        assert_eq!(Milliwatts(71999).checked_to_watts(), Some(Watts(71)));
+        assert_eq!(Milliwatts(256000).checked_to_watts(), None);
+    }
+
+    #[test]
+    fn microamps() {
+        assert_eq!(Microamps(2500).to_milliamps(), Milliamps(2));
+        assert_eq!(Milliamps(3).checked_to_microamps(), Some(Microamps(3000)));
+        assert_eq!(Milliamps(u32::MAX).checked_to_microamps(), None);
+    }
+
+    #[test]
+    fn checked_arithmetic() {
+        assert_eq!(
+            Millivolts(5000).checked_add(Millivolts(4000)),
+            Some(Millivolts(9000))
+        );
+        assert_eq!(Milliamps(1).checked_sub(Milliamps(2)), None);
+        assert_eq!(Watts(200).checked_mul(2), None);
+        assert_eq!(Milliamps(1).saturating_sub(Milliamps(2)), Milliamps::ZERO);
+    }
+}